@@ -0,0 +1,187 @@
+use chrono::{Days, Local, LocalResult, NaiveDate, TimeZone};
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Source of time for the scheduler, so the break cycle can be driven by a fake
+/// clock in tests.
+pub trait Clock: Send + Sync {
+    /// Current time as a Unix timestamp in milliseconds.
+    fn now_ms(&self) -> u64;
+
+    /// Milliseconds on a clock that never jumps and stops while the machine is
+    /// suspended.
+    fn awake_ms(&self) -> u64;
+
+    /// Milliseconds on a clock that never jumps but keeps running while suspended,
+    /// or `None` if the platform doesn't have one.
+    fn boot_ms(&self) -> Option<u64>;
+
+    fn read(&self) -> ClockReading {
+        ClockReading {
+            wall_ms: self.now_ms(),
+            awake_ms: self.awake_ms(),
+            boot_ms: self.boot_ms(),
+        }
+    }
+}
+
+/// The three clocks read together, to tell suspends and wall-clock changes apart
+/// from time simply passing.
+#[derive(Debug, Clone, Copy)]
+pub struct ClockReading {
+    pub wall_ms: u64,
+    pub awake_ms: u64,
+    pub boot_ms: Option<u64>,
+}
+
+impl ClockReading {
+    /// How long the machine was suspended since `earlier`, and by how much the wall
+    /// clock was moved (positive: forwards).
+    ///
+    /// Without a boot clock a forward wall-clock change can't be told from a suspend
+    /// and is reported as one.
+    pub fn since(&self, earlier: &ClockReading) -> (u64, i64) {
+        let wall = self.wall_ms as i64 - earlier.wall_ms as i64;
+        let awake = self.awake_ms.saturating_sub(earlier.awake_ms) as i64;
+        match (self.boot_ms, earlier.boot_ms) {
+            (Some(boot), Some(earlier_boot)) => {
+                let boot = boot.saturating_sub(earlier_boot) as i64;
+                ((boot - awake).max(0) as u64, wall - boot)
+            }
+            _ if wall >= awake => ((wall - awake) as u64, 0),
+            _ => (0, wall - awake),
+        }
+    }
+}
+
+/// Start of the next local calendar day after `now_ms` (Unix ms). If midnight doesn't
+/// exist that day (a DST jump), the first valid time after it is used.
+pub fn next_local_midnight(now_ms: u64) -> u64 {
+    local_date(&Local, now_ms)
+        .and_then(|today| local_instant(&Local, today + Days::new(1), 0))
+        .unwrap_or(now_ms + 24 * 60 * 60 * 1000)
+}
+
+/// Minutes since midnight for a time of day written `"HH:MM"` (`"24:00"` allowed).
+pub fn parse_time_of_day(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.split_once(':')?;
+    if hours.len() != 2 || minutes.len() != 2 {
+        return None;
+    }
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    match (hours, minutes) {
+        (24, 0) => Some(24 * 60),
+        (0..=23, 0..=59) => Some(hours * 60 + minutes),
+        _ => None,
+    }
+}
+
+/// The calendar date in `tz` at `ms` (Unix ms).
+pub fn local_date<Tz: TimeZone>(tz: &Tz, ms: u64) -> Option<NaiveDate> {
+    Some(tz.timestamp_millis_opt(ms as i64).single()?.date_naive())
+}
+
+/// The instant (Unix ms) `minutes` after midnight in `tz` on `date`. A time skipped by
+/// a DST jump maps to the first valid time after the gap; a repeated one to its first
+/// occurrence.
+pub fn local_instant<Tz: TimeZone>(tz: &Tz, date: NaiveDate, minutes: u32) -> Option<u64> {
+    let local = date.and_hms_opt(0, 0, 0)? + chrono::Duration::minutes(minutes as i64);
+    let instant = match tz.from_local_datetime(&local) {
+        LocalResult::Single(instant) | LocalResult::Ambiguous(instant, _) => instant,
+        LocalResult::None => tz.from_local_datetime(&(local + chrono::Duration::hours(1))).earliest()?,
+    };
+    u64::try_from(instant.timestamp_millis()).ok()
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64
+    }
+
+    fn awake_ms(&self) -> u64 {
+        // Instant doesn't advance during suspend on Linux and macOS
+        static START: OnceLock<Instant> = OnceLock::new();
+        START.get_or_init(Instant::now).elapsed().as_millis() as u64
+    }
+
+    #[cfg(target_os = "linux")]
+    fn boot_ms(&self) -> Option<u64> {
+        let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+        if unsafe { libc::clock_gettime(libc::CLOCK_BOOTTIME, &mut ts) } != 0 {
+            return None;
+        }
+        Some(ts.tv_sec as u64 * 1000 + ts.tv_nsec as u64 / 1_000_000)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn boot_ms(&self) -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(test)]
+pub use manual::ManualClock;
+
+#[cfg(test)]
+mod manual {
+    use super::Clock;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// Clock that only moves when told to. Clones share the same time.
+    #[derive(Clone)]
+    pub struct ManualClock {
+        now: Arc<AtomicU64>,
+        awake: Arc<AtomicU64>,
+        boot: Arc<AtomicU64>,
+    }
+
+    impl ManualClock {
+        pub fn new(now_ms: u64) -> Self {
+            Self {
+                now: Arc::new(AtomicU64::new(now_ms)),
+                awake: Arc::new(AtomicU64::new(0)),
+                boot: Arc::new(AtomicU64::new(0)),
+            }
+        }
+
+        /// Change the wall clock only, like a manual time change or NTP jump.
+        pub fn set(&self, now_ms: u64) {
+            self.now.store(now_ms, Ordering::SeqCst);
+        }
+
+        pub fn advance(&self, duration: Duration) {
+            let ms = duration.as_millis() as u64;
+            self.now.fetch_add(ms, Ordering::SeqCst);
+            self.awake.fetch_add(ms, Ordering::SeqCst);
+            self.boot.fetch_add(ms, Ordering::SeqCst);
+        }
+
+        /// Let time pass with the machine asleep.
+        pub fn suspend(&self, duration: Duration) {
+            let ms = duration.as_millis() as u64;
+            self.now.fetch_add(ms, Ordering::SeqCst);
+            self.boot.fetch_add(ms, Ordering::SeqCst);
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now_ms(&self) -> u64 {
+            self.now.load(Ordering::SeqCst)
+        }
+
+        fn awake_ms(&self) -> u64 {
+            self.awake.load(Ordering::SeqCst)
+        }
+
+        fn boot_ms(&self) -> Option<u64> {
+            Some(self.boot.load(Ordering::SeqCst))
+        }
+    }
+}
@@ -1,45 +1,92 @@
-use crate::models::{BreakSettings, BreakState};
+use crate::export::ExportFormat;
+use crate::history::{HistorySummary, SummaryPeriod};
+use crate::import::ImportReport;
+use crate::models::{BreakSettings, BreakState, SchedulerState};
+use crate::profiles::ProfileList;
 use tauri::{Manager, State};
+use tauri_plugin_opener::OpenerExt;
 
 #[tauri::command]
 pub fn start_break(app_state: State<'_, crate::state::AppState>) -> Result<BreakState, String> {
     let scheduler = app_state.scheduler.lock().unwrap();
-    scheduler.start_break();
+    scheduler.start_break().map_err(|e| e.to_string())?;
     Ok(scheduler.get_state())
 }
 
+/// Pause for `minutes`, until `until` (Unix ms), or until resumed if neither is given.
 #[tauri::command]
-pub fn pause_breaks(app_state: State<'_, crate::state::AppState>) -> Result<BreakState, String> {
+pub fn pause_breaks(
+    minutes: Option<u32>,
+    until: Option<u64>,
+    app_state: State<'_, crate::state::AppState>,
+) -> Result<BreakState, String> {
     let scheduler = app_state.scheduler.lock().unwrap();
-    scheduler.pause();
+    let until = until.or_else(|| minutes.map(|minutes| scheduler.now_ms() + minutes as u64 * 60 * 1000));
+    scheduler.pause(until).map_err(|e| e.to_string())?;
     Ok(scheduler.get_state())
 }
 
 #[tauri::command]
 pub fn resume_breaks(app_state: State<'_, crate::state::AppState>) -> Result<BreakState, String> {
     let scheduler = app_state.scheduler.lock().unwrap();
-    scheduler.resume();
+    scheduler.resume().map_err(|e| e.to_string())?;
     Ok(scheduler.get_state())
 }
 
 #[tauri::command]
 pub fn skip_break(app_state: State<'_, crate::state::AppState>) -> Result<BreakState, String> {
     let scheduler = app_state.scheduler.lock().unwrap();
-    scheduler.skip_break();
+    scheduler.skip_break().map_err(|e| e.to_string())?;
     Ok(scheduler.get_state())
 }
 
 #[tauri::command]
-pub fn postpone_break(app_state: State<'_, crate::state::AppState>) -> Result<BreakState, String> {
+pub fn postpone_break(minutes: Option<u32>, app_state: State<'_, crate::state::AppState>) -> Result<BreakState, String> {
+    // Without a choice, take the shortest configured delay
+    let minutes = match minutes {
+        Some(minutes) => minutes,
+        None => app_state.settings.lock().unwrap().postpone_durations_minutes[0],
+    };
     let scheduler = app_state.scheduler.lock().unwrap();
-    scheduler.postpone_break();
+    scheduler.postpone_break(minutes).map_err(|e| e.to_string())?;
     Ok(scheduler.get_state())
 }
 
 #[tauri::command]
 pub fn complete_break(app_state: State<'_, crate::state::AppState>) -> Result<BreakState, String> {
     let scheduler = app_state.scheduler.lock().unwrap();
-    scheduler.complete_break();
+    scheduler.complete_break().map_err(|e| e.to_string())?;
+    Ok(scheduler.get_state())
+}
+
+/// Start a pomodoro, with an optional task label.
+#[tauri::command]
+pub fn start_pomodoro(
+    label: Option<String>,
+    app: tauri::AppHandle,
+    app_state: State<'_, crate::state::AppState>,
+) -> Result<BreakState, String> {
+    let state = {
+        let scheduler = app_state.scheduler.lock().unwrap();
+        scheduler.start_pomodoro(label).map_err(|e| e.to_string())?;
+        scheduler.get_state()
+    };
+    // The tray offers recent task labels
+    crate::tray::refresh_menu(&app);
+    Ok(state)
+}
+
+#[tauri::command]
+pub fn interrupt_pomodoro(app_state: State<'_, crate::state::AppState>) -> Result<BreakState, String> {
+    let scheduler = app_state.scheduler.lock().unwrap();
+    scheduler.interrupt_pomodoro().map_err(|e| e.to_string())?;
+    Ok(scheduler.get_state())
+}
+
+#[tauri::command]
+pub fn void_pomodoro(app_state: State<'_, crate::state::AppState>) -> Result<BreakState, String> {
+    let scheduler = app_state.scheduler.lock().unwrap();
+    scheduler.void_pomodoro().map_err(|e| e.to_string())?;
     Ok(scheduler.get_state())
 }
 
@@ -50,20 +97,77 @@ pub fn get_break_state(app_state: State<'_, crate::state::AppState>) -> Result<B
 }
 
 #[tauri::command]
-pub fn update_settings(settings: BreakSettings, app_state: State<'_, crate::state::AppState>) -> Result<BreakSettings, String> {
+pub fn get_scheduler_state(app_state: State<'_, crate::state::AppState>) -> Result<SchedulerState, String> {
+    let scheduler = app_state.scheduler.lock().unwrap();
+    Ok(scheduler.get_scheduler_state())
+}
+
+/// Most periods `get_history_summaries` reports on at once.
+const MAX_SUMMARY_PERIODS: u32 = 366;
+
+#[tauri::command]
+pub fn get_history_summaries(
+    period: SummaryPeriod,
+    count: u32,
+    app_state: State<'_, crate::state::AppState>,
+) -> Result<Vec<HistorySummary>, String> {
+    let now = app_state.scheduler.lock().unwrap().now_ms();
+    let records = crate::history::load(&app_state.history_path);
+    Ok(crate::history::summaries(&records, period, count.min(MAX_SUMMARY_PERIODS), now))
+}
+
+/// Export the history between the local dates `from` and `to` (`YYYY-MM-DD`, both
+/// included) to a new file in the downloads folder. Returns the file written.
+#[tauri::command]
+pub fn export_history(from: String, to: String, format: ExportFormat, app: tauri::AppHandle) -> Result<String, String> {
+    let parse = |date: &str| {
+        chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|e| format!("Invalid date {:?}: {}", date, e))
+    };
+    let path = crate::export::export_history(&app, parse(&from)?, parse(&to)?, format)?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Write the report for last week now, whether or not reports are on. Returns the
+/// file written.
+#[tauri::command]
+pub fn write_weekly_report(app_state: State<'_, crate::state::AppState>) -> Result<String, String> {
+    let scheduler = app_state.scheduler.lock().unwrap();
+    let today = crate::clock::local_date(&chrono::Local, scheduler.now_ms()).ok_or("Invalid current time")?;
+    let last_week = crate::history::week_start(today) - chrono::Days::new(7);
+    let path = scheduler.write_report(last_week)?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+#[tauri::command]
+pub fn open_latest_report(app: tauri::AppHandle, app_state: State<'_, crate::state::AppState>) -> Result<(), String> {
+    let settings = app_state.settings.lock().unwrap().reports.clone();
+    let dir = crate::report::reports_dir(Some(&app), &settings).ok_or("No folder for reports")?;
+    let path = crate::report::latest_report(&dir).ok_or("No report written yet")?;
+    app.opener().open_path(path.to_string_lossy(), None::<&str>).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_settings(settings: BreakSettings, app: tauri::AppHandle) -> Result<BreakSettings, String> {
+    apply_settings(&app, settings)
+}
+
+/// Validate, apply and save `settings`, rescheduling breaks; they also become the
+/// active profile's settings. Shared by `update_settings` and importing.
+pub fn apply_settings(app: &tauri::AppHandle, settings: BreakSettings) -> Result<BreakSettings, String> {
+    let profiles = app.state::<crate::state::AppState>().profiles.lock().unwrap().clone();
+    apply_settings_with(app, settings, profiles)
+}
+
+/// `apply_settings`, with `profiles` taking the place of the current ones once the
+/// settings are in use. Nothing changes if the settings are invalid.
+fn apply_settings_with(
+    app: &tauri::AppHandle,
+    settings: BreakSettings,
+    mut profiles: crate::profiles::Profiles,
+) -> Result<BreakSettings, String> {
+    let app_state = app.state::<crate::state::AppState>();
     // Backend validation (safety net)
-    if settings.microbreak_interval_minutes < 1 || settings.microbreak_interval_minutes > 60 {
-        return Err("Invalid microbreak interval: must be 1-60 minutes".to_string());
-    }
-    if settings.microbreak_duration_seconds < 5 || settings.microbreak_duration_seconds > 300 {
-        return Err("Invalid microbreak duration: must be 5-300 seconds".to_string());
-    }
-    if settings.longbreak_interval_microbreaks < 1 || settings.longbreak_interval_microbreaks > 10 {
-        return Err("Invalid long break interval: must be 1-10 microbreaks".to_string());
-    }
-    if settings.longbreak_duration_minutes < 1 || settings.longbreak_duration_minutes > 60 {
-        return Err("Invalid long break duration: must be 1-60 minutes".to_string());
-    }
+    settings.validate()?;
 
     println!("Settings validated: {:?}", settings);
 
@@ -81,15 +185,116 @@ pub fn update_settings(settings: BreakSettings, app_state: State<'_, crate::stat
         println!("Rescheduled breaks with new settings");
     }
 
+    crate::config::save_settings(&app_state.settings_path, &settings)?;
+    println!("Settings saved to {:?}", app_state.settings_path);
+    {
+        let mut current = app_state.profiles.lock().unwrap();
+        profiles.update_active(&settings);
+        if profiles != *current {
+            // Kept even if saving fails, as these are the settings in use
+            *current = profiles;
+            crate::profiles::save(&app_state.profiles_path, &current)?;
+        }
+    }
+    crate::events::emit_settings_changed(app, &settings);
+    crate::tray::refresh_menu(app);
+
     Ok(settings)
 }
 
+#[tauri::command]
+pub fn get_profiles(app_state: State<'_, crate::state::AppState>) -> Result<ProfileList, String> {
+    Ok(app_state.profiles.lock().unwrap().list())
+}
+
+/// Save the current settings as a new profile, which becomes the active one.
+#[tauri::command]
+pub fn create_profile(name: String, app: tauri::AppHandle) -> Result<ProfileList, String> {
+    let settings = app.state::<crate::state::AppState>().settings.lock().unwrap().clone();
+    change_profiles(&app, |profiles| profiles.create(&name, settings))
+}
+
+#[tauri::command]
+pub fn rename_profile(from: String, to: String, app: tauri::AppHandle) -> Result<ProfileList, String> {
+    change_profiles(&app, |profiles| profiles.rename(&from, &to))
+}
+
+#[tauri::command]
+pub fn delete_profile(name: String, app: tauri::AppHandle) -> Result<ProfileList, String> {
+    change_profiles(&app, |profiles| profiles.delete(&name))
+}
+
+#[tauri::command]
+pub fn activate_profile(name: String, app: tauri::AppHandle) -> Result<BreakSettings, String> {
+    activate(&app, &name)
+}
+
+/// Switch to the profile `name`, going through the same path as `update_settings`.
+/// The profile only becomes the active one once its settings are applied.
+pub fn activate(app: &tauri::AppHandle, name: &str) -> Result<BreakSettings, String> {
+    let app_state = app.state::<crate::state::AppState>();
+    let mut profiles = app_state.profiles.lock().unwrap().clone();
+    let settings = profiles.activate(name)?;
+    let settings = apply_settings_with(app, settings, profiles)?;
+    println!("Switched to profile {}", name);
+    let list = app_state.profiles.lock().unwrap().list();
+    crate::events::emit_profiles_changed(app, &list);
+    Ok(settings)
+}
+
+/// Import a Stretchly `config.json` or Workrave settings export at `path` into the
+/// current settings, or into a new profile called `profile`.
+#[tauri::command]
+pub fn import_settings(path: String, profile: Option<String>, app: tauri::AppHandle) -> Result<ImportReport, String> {
+    let contents = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let current = app.state::<crate::state::AppState>().settings.lock().unwrap().clone();
+    let mut report = crate::import::import(&contents, current)?;
+    println!("Imported {:?} settings, left out: {:?}", report.source, report.unmapped);
+
+    match profile {
+        Some(name) => {
+            let settings = report.settings.clone();
+            change_profiles(&app, |profiles| {
+                report.profile = Some(profiles.add(&name, settings)?);
+                Ok(())
+            })?;
+        }
+        None => report.settings = apply_settings(&app, report.settings)?,
+    }
+    Ok(report)
+}
+
+/// Apply `change` to the profiles, save them and let the windows and tray know.
+fn change_profiles(
+    app: &tauri::AppHandle,
+    change: impl FnOnce(&mut crate::profiles::Profiles) -> Result<(), String>,
+) -> Result<ProfileList, String> {
+    let app_state = app.state::<crate::state::AppState>();
+    let list = {
+        let mut profiles = app_state.profiles.lock().unwrap();
+        // Only keep the change if it could be saved
+        let mut changed = profiles.clone();
+        change(&mut changed)?;
+        crate::profiles::save(&app_state.profiles_path, &changed)?;
+        *profiles = changed;
+        profiles.list()
+    };
+    crate::events::emit_profiles_changed(app, &list);
+    crate::tray::refresh_menu(app);
+    Ok(list)
+}
+
 #[tauri::command]
 pub fn get_settings(app_state: State<'_, crate::state::AppState>) -> Result<BreakSettings, String> {
     let settings = app_state.settings.lock().unwrap();
     Ok(settings.clone())
 }
 
+#[tauri::command]
+pub fn get_settings_notes(app_state: State<'_, crate::state::AppState>) -> Result<Vec<String>, String> {
+    Ok(app_state.settings_notes.clone())
+}
+
 #[tauri::command]
 pub fn set_break_window_mode(app: tauri::AppHandle) -> Result<(), String> {
     if let Some(window) = app.get_webview_window("main") {
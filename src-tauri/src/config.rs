@@ -0,0 +1,393 @@
+use crate::models::BreakSettings;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const SETTINGS_FILE: &str = "settings.json";
+
+/// A migration rewrites the raw JSON object of schema version `n` into version `n + 1`.
+type Migration = fn(&mut Map<String, Value>);
+
+/// `MIGRATIONS[n - 1]` upgrades a file from schema version `n` to `n + 1`.
+/// When `BreakSettings` gains, loses or renames a field, append a step here
+/// instead of bumping the version by hand.
+const MIGRATIONS: &[Migration] = &[
+    add_count_active_time_only,
+    add_longbreak_schedule,
+    add_postpone_options,
+    add_strict_mode,
+    add_escalation,
+    add_working_hours,
+    add_fixed_breaks,
+    add_break_kinds,
+    add_pomodoro,
+    add_break_warning,
+    add_reports,
+];
+
+/// Schema version written to new files. Version 1 is the first persisted layout.
+pub const SCHEMA_VERSION: u32 = 1 + MIGRATIONS.len() as u32;
+
+/// v1 -> v2: intervals could only be wall-clock time.
+fn add_count_active_time_only(fields: &mut Map<String, Value>) {
+    fields.insert("count_active_time_only".to_string(), Value::Bool(false));
+}
+
+/// v2 -> v3: long breaks could only follow a number of microbreaks.
+fn add_longbreak_schedule(fields: &mut Map<String, Value>) {
+    fields.insert("longbreak_schedule".to_string(), Value::from("Microbreaks"));
+    fields.insert("longbreak_interval_minutes".to_string(), Value::from(90));
+}
+
+/// v3 -> v4: postponing was fixed at five minutes, without limits.
+fn add_postpone_options(fields: &mut Map<String, Value>) {
+    fields.insert("postpone_durations_minutes".to_string(), Value::from(vec![5]));
+    fields.insert("max_postpones_per_break".to_string(), Value::from(10));
+    fields.insert("postpone_cooldown_seconds".to_string(), Value::from(0));
+}
+
+/// v4 -> v5: every break could be skipped.
+fn add_strict_mode(fields: &mut Map<String, Value>) {
+    fields.insert("strict_microbreaks".to_string(), Value::Bool(false));
+    fields.insert("strict_longbreaks".to_string(), Value::Bool(false));
+}
+
+/// v5 -> v6: dodging breaks had no consequences.
+fn add_escalation(fields: &mut Map<String, Value>) {
+    let defaults = BreakSettings::default();
+    fields.insert("escalation_after_dodges".to_string(), Value::from(0));
+    fields.insert(
+        "escalation_steps".to_string(),
+        serde_json::to_value(&defaults.escalation_steps).unwrap_or_default(),
+    );
+    fields.insert("escalation_extra_seconds".to_string(), Value::from(defaults.escalation_extra_seconds));
+}
+
+/// v6 -> v7: breaks ran around the clock.
+fn add_working_hours(fields: &mut Map<String, Value>) {
+    fields.insert(
+        "working_hours".to_string(),
+        serde_json::to_value(crate::working_hours::WorkingHours::default()).unwrap_or_default(),
+    );
+}
+
+/// v7 -> v8: only interval breaks.
+fn add_fixed_breaks(fields: &mut Map<String, Value>) {
+    fields.insert("fixed_breaks".to_string(), Value::Array(Vec::new()));
+}
+
+/// v8 -> v9: microbreaks and long breaks were the only kinds of break.
+fn add_break_kinds(fields: &mut Map<String, Value>) {
+    fields.insert("break_kinds".to_string(), Value::Array(Vec::new()));
+}
+
+/// v9 -> v10: no Pomodoro mode.
+fn add_pomodoro(fields: &mut Map<String, Value>) {
+    fields.insert(
+        "pomodoro".to_string(),
+        serde_json::to_value(crate::models::PomodoroSettings::default()).unwrap_or_default(),
+    );
+}
+
+/// v10 -> v11: breaks started without warning.
+fn add_break_warning(fields: &mut Map<String, Value>) {
+    fields.insert("warning_seconds".to_string(), Value::from(0));
+}
+
+/// v11 -> v12: there were no weekly reports.
+fn add_reports(fields: &mut Map<String, Value>) {
+    fields.insert(
+        "reports".to_string(),
+        serde_json::to_value(crate::models::ReportSettings::default()).unwrap_or_default(),
+    );
+}
+
+/// Result of reading the settings file. `notes` lists everything the loader had to
+/// fix up (migrations applied, fields reset to defaults, unreadable files, ...).
+pub struct LoadedSettings {
+    pub settings: BreakSettings,
+    pub notes: Vec<String>,
+}
+
+pub fn settings_path(config_dir: &Path) -> PathBuf {
+    config_dir.join(SETTINGS_FILE)
+}
+
+/// Load settings from `path`, never failing: anything that can't be used falls back
+/// to the default for that field (or for the whole file) and is reported in `notes`.
+/// If the file needed fixing it is rewritten, keeping a `.bak` copy of the original.
+pub fn load_settings(path: &Path) -> LoadedSettings {
+    let mut notes = Vec::new();
+
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            println!("No settings file at {:?}, using defaults", path);
+            return LoadedSettings { settings: BreakSettings::default(), notes };
+        }
+        Err(e) => {
+            notes.push(format!("Could not read settings file, using defaults: {}", e));
+            return LoadedSettings { settings: BreakSettings::default(), notes };
+        }
+    };
+
+    let fields = match serde_json::from_str::<Value>(&contents) {
+        Ok(Value::Object(fields)) => Some(fields),
+        Ok(_) => {
+            notes.push("Settings file is not a JSON object, using defaults".to_string());
+            None
+        }
+        Err(e) => {
+            notes.push(format!("Settings file is corrupt, using defaults: {}", e));
+            None
+        }
+    };
+
+    let settings = match fields {
+        Some(fields) => parse_settings(fields, &mut notes),
+        None => BreakSettings::default(),
+    };
+
+    if !notes.is_empty() {
+        for note in &notes {
+            println!("Settings: {}", note);
+        }
+        let backup = path.with_extension("json.bak");
+        if let Err(e) = fs::copy(path, &backup) {
+            println!("Warning: failed to back up settings file to {:?}: {}", backup, e);
+        }
+        if let Err(e) = save_settings(path, &settings) {
+            println!("Warning: failed to rewrite settings file: {}", e);
+        }
+    }
+
+    LoadedSettings { settings, notes }
+}
+
+/// Write settings atomically (temp file + rename) with the current `schema_version`.
+pub fn save_settings(path: &Path, settings: &BreakSettings) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(&settings_to_json(settings)?)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    write_atomic(path, json.as_bytes())
+}
+
+/// `settings` as stored on disk, tagged with the current `schema_version`.
+pub fn settings_to_json(settings: &BreakSettings) -> Result<Value, String> {
+    let mut fields = match serde_json::to_value(settings) {
+        Ok(Value::Object(fields)) => fields,
+        Ok(_) => return Err("Settings did not serialize to a JSON object".to_string()),
+        Err(e) => return Err(format!("Failed to serialize settings: {}", e)),
+    };
+    fields.insert("schema_version".to_string(), Value::from(SCHEMA_VERSION));
+    Ok(Value::Object(fields))
+}
+
+/// Read settings stored by `settings_to_json`, migrating and repairing them like the
+/// settings file; what had to be fixed goes to `notes`.
+pub fn settings_from_json(value: Value, notes: &mut Vec<String>) -> BreakSettings {
+    match value {
+        Value::Object(fields) => parse_settings(fields, notes),
+        _ => {
+            notes.push("Settings are not a JSON object, using defaults".to_string());
+            BreakSettings::default()
+        }
+    }
+}
+
+/// Write `contents` to a sibling temp file and rename it over `path`, so a crash
+/// mid-write never leaves a truncated file behind.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    }
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, contents).map_err(|e| format!("Failed to write {:?}: {}", tmp, e))?;
+    fs::rename(&tmp, path).map_err(|e| format!("Failed to replace {:?}: {}", path, e))
+}
+
+fn parse_settings(mut fields: Map<String, Value>, notes: &mut Vec<String>) -> BreakSettings {
+    let version = match fields.remove("schema_version") {
+        Some(value) => match value.as_u64() {
+            Some(version) if version >= 1 => version as u32,
+            _ => {
+                notes.push(format!("Invalid schema_version {}, assuming version 1", value));
+                1
+            }
+        },
+        None => {
+            notes.push("Settings file has no schema_version, assuming version 1".to_string());
+            1
+        }
+    };
+
+    if version > SCHEMA_VERSION {
+        notes.push(format!(
+            "Settings file is from a newer version (schema {}, supported {}), loading known fields only",
+            version, SCHEMA_VERSION
+        ));
+    } else {
+        for (step, migrate) in MIGRATIONS.iter().enumerate().skip(version as usize - 1) {
+            migrate(&mut fields);
+            notes.push(format!("Migrated settings from schema {} to {}", step + 1, step + 2));
+        }
+    }
+
+    merge_with_defaults(fields, notes)
+}
+
+/// Take each known field from `fields` only if it parses and is in range on its own;
+/// everything else keeps its default. Fields that conflict with each other once merged
+/// are reset to their defaults until they no longer do.
+fn merge_with_defaults(mut fields: Map<String, Value>, notes: &mut Vec<String>) -> BreakSettings {
+    let defaults = match serde_json::to_value(BreakSettings::default()) {
+        Ok(Value::Object(defaults)) => defaults,
+        _ => unreachable!("BreakSettings always serializes to an object"),
+    };
+    let mut merged = defaults.clone();
+
+    for key in defaults.keys() {
+        let Some(value) = fields.remove(key) else {
+            notes.push(format!("`{}` missing, using default", key));
+            continue;
+        };
+
+        let mut candidate = defaults.clone();
+        candidate.insert(key.clone(), value.clone());
+        match serde_json::from_value::<BreakSettings>(Value::Object(candidate)) {
+            Ok(settings) => match settings.validate_ranges() {
+                Ok(()) => {
+                    merged.insert(key.clone(), value);
+                }
+                Err(e) => notes.push(format!("`{}` reset to default: {}", key, e)),
+            },
+            Err(e) => notes.push(format!("`{}` reset to default: {}", key, e)),
+        }
+    }
+
+    for key in fields.keys() {
+        notes.push(format!("Ignored unknown setting `{}`", key));
+    }
+
+    // Each field is valid on its own; combinations can still conflict. Reset the
+    // fields involved until they don't.
+    loop {
+        let settings = match serde_json::from_value::<BreakSettings>(Value::Object(merged.clone())) {
+            Ok(settings) => settings,
+            Err(e) => {
+                notes.push(format!("Settings could not be loaded, using defaults: {}", e));
+                return BreakSettings::default();
+            }
+        };
+        let Some((message, keys)) = settings.conflict() else {
+            return match settings.validate() {
+                Ok(()) => settings,
+                Err(e) => {
+                    notes.push(format!("Settings conflict, using defaults: {}", e));
+                    BreakSettings::default()
+                }
+            };
+        };
+        let Some(key) = keys.iter().find(|key| merged.get(**key) != defaults.get(**key)) else {
+            notes.push(format!("Settings conflict, using defaults: {}", message));
+            return BreakSettings::default();
+        };
+        merged.insert(key.to_string(), defaults[*key].clone());
+        notes.push(format!("`{}` reset to default: {}", key, message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(value: Value) -> (BreakSettings, Vec<String>) {
+        let Value::Object(fields) = value else { panic!("not an object") };
+        let mut notes = Vec::new();
+        (parse_settings(fields, &mut notes), notes)
+    }
+
+    #[test]
+    fn v1_file_is_migrated_without_resetting_fields() {
+        let (settings, notes) = parse(json!({
+            "schema_version": 1,
+            "microbreak_interval_minutes": 30,
+            "microbreak_duration_seconds": 20,
+            "longbreak_interval_microbreaks": 4,
+            "longbreak_duration_minutes": 5,
+            "fullscreen_breaks": true,
+        }));
+
+        assert_eq!(settings.microbreak_interval_minutes, 30);
+        assert!(settings.fullscreen_breaks);
+        assert!(!settings.count_active_time_only);
+        assert_eq!(
+            notes,
+            vec![
+                "Migrated settings from schema 1 to 2".to_string(),
+                "Migrated settings from schema 2 to 3".to_string(),
+                "Migrated settings from schema 3 to 4".to_string(),
+                "Migrated settings from schema 4 to 5".to_string(),
+                "Migrated settings from schema 5 to 6".to_string(),
+                "Migrated settings from schema 6 to 7".to_string(),
+                "Migrated settings from schema 7 to 8".to_string(),
+                "Migrated settings from schema 8 to 9".to_string(),
+                "Migrated settings from schema 9 to 10".to_string(),
+                "Migrated settings from schema 10 to 11".to_string(),
+                "Migrated settings from schema 11 to 12".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_field_falls_back_to_its_default_only() {
+        let mut fields = serde_json::to_value(BreakSettings::default()).unwrap();
+        fields["schema_version"] = json!(SCHEMA_VERSION);
+        fields["microbreak_interval_minutes"] = json!(0);
+        fields["fullscreen_breaks"] = json!(true);
+
+        let (settings, notes) = parse(fields);
+
+        assert_eq!(settings.microbreak_interval_minutes, 20);
+        assert!(settings.fullscreen_breaks);
+        assert_eq!(notes.len(), 1);
+    }
+
+    #[test]
+    fn fields_are_not_judged_against_the_other_defaults() {
+        // A 300 s microbreak is only valid next to a long break over 5 minutes
+        let mut fields = serde_json::to_value(BreakSettings::default()).unwrap();
+        fields["schema_version"] = json!(SCHEMA_VERSION);
+        fields["microbreak_duration_seconds"] = json!(300);
+        fields["longbreak_duration_minutes"] = json!(10);
+
+        let (settings, notes) = parse(fields);
+
+        assert_eq!(settings.microbreak_duration_seconds, 300);
+        assert_eq!(settings.longbreak_duration_minutes, 10);
+        assert_eq!(notes, Vec::<String>::new());
+    }
+
+    #[test]
+    fn conflicting_v1_file_keeps_the_fields_not_in_conflict() {
+        let (settings, notes) = parse(json!({
+            "schema_version": 1,
+            "microbreak_interval_minutes": 30,
+            "microbreak_duration_seconds": 300,
+            "longbreak_interval_microbreaks": 6,
+            "longbreak_duration_minutes": 5,
+            "fullscreen_breaks": true,
+        }));
+
+        assert_eq!(settings.microbreak_interval_minutes, 30);
+        assert_eq!(settings.longbreak_interval_microbreaks, 6);
+        assert!(settings.fullscreen_breaks);
+        assert_eq!(settings.microbreak_duration_seconds, BreakSettings::default().microbreak_duration_seconds);
+        assert_eq!(settings.longbreak_duration_minutes, 5);
+        assert_eq!(
+            notes.last(),
+            Some(&"`microbreak_duration_seconds` reset to default: Long breaks must be longer than microbreaks".to_string())
+        );
+        assert!(!notes.iter().any(|note| note.starts_with("Settings conflict")));
+    }
+}
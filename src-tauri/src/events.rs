@@ -0,0 +1,93 @@
+//! Events pushed to the webviews, so windows stay in sync without polling.
+//! `src/lib/events.ts` mirrors these names and payloads; keep the two in step.
+//!
+//! | Event                 | Payload            | Sent when                                         |
+//! |-----------------------|--------------------|---------------------------------------------------|
+//! | `break-state-changed` | [`SchedulerEvent`] | any lifecycle transition or change to the schedule |
+//! | `break-tick`          | [`BreakTick`]      | every second while a break is on screen           |
+//! | `settings-changed`    | `BreakSettings`    | settings were saved                               |
+//! | `profiles-changed`    | [`ProfileList`]    | a profile was created, renamed, deleted or activated |
+
+use crate::models::{BreakSettings, BreakState, BreakType, SchedulerState};
+use crate::profiles::ProfileList;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+pub const BREAK_STATE_CHANGED: &str = "break-state-changed";
+pub const BREAK_TICK: &str = "break-tick";
+pub const SETTINGS_CHANGED: &str = "settings-changed";
+pub const PROFILES_CHANGED: &str = "profiles-changed";
+
+/// Payload of `break-state-changed`: the lifecycle and the upcoming break together,
+/// as `get_break_state` and the tray would report them.
+#[derive(Debug, Clone, Serialize)]
+pub struct SchedulerEvent {
+    pub state: BreakState,
+    pub schedule: SchedulerState,
+}
+
+/// Payload of `break-tick`. Times are Unix ms.
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakTick {
+    pub break_type: Option<BreakType>,
+    pub started_at: u64,
+    pub ends_at: u64,
+    pub time_left_ms: u64,
+}
+
+pub fn emit_state_changed(app: &AppHandle, payload: &SchedulerEvent) {
+    emit(app, BREAK_STATE_CHANGED, payload);
+}
+
+pub fn emit_tick(app: &AppHandle, payload: &BreakTick) {
+    emit(app, BREAK_TICK, payload);
+}
+
+pub fn emit_settings_changed(app: &AppHandle, settings: &BreakSettings) {
+    emit(app, SETTINGS_CHANGED, settings);
+}
+
+pub fn emit_profiles_changed(app: &AppHandle, profiles: &ProfileList) {
+    emit(app, PROFILES_CHANGED, profiles);
+}
+
+fn emit<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    if let Err(e) = app.emit(event, payload) {
+        println!("Warning: failed to emit {}: {}", event, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn state_changed_payload_matches_the_documented_schema() {
+        let payload = SchedulerEvent {
+            state: BreakState::default(),
+            schedule: SchedulerState {
+                scheduled_break_time: Some(1000),
+                current_break_type: Some(BreakType::Microbreak),
+                time_left_ms: Some(500),
+                active_time_only: false,
+                postpones_left: 3,
+                postpone_available_at: None,
+            },
+        };
+
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(value["state"]["phase"], json!("Scheduled"));
+        assert_eq!(
+            value["schedule"],
+            json!({
+                "scheduled_break_time": 1000,
+                "current_break_type": "Microbreak",
+                "time_left_ms": 500,
+                "active_time_only": false,
+                "postpones_left": 3,
+                "postpone_available_at": null,
+            })
+        );
+    }
+}
@@ -0,0 +1,393 @@
+use crate::clock::local_instant;
+use crate::history::{HistoryEvent, HistoryRecord, Interruption};
+use crate::fixed_breaks::FixedBreak;
+use crate::models::{BreakKind, BreakSettings, BreakType, EscalationStep, LongbreakSchedule, PomodoroMode};
+use chrono::{Local, NaiveDate, SecondsFormat, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+/// How many exports of the same range may pile up before giving up on a free name.
+const MAX_SAME_NAME_EXPORTS: u32 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    Csv,
+    JsonLines,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::JsonLines => "jsonl",
+        }
+    }
+}
+
+/// Columns of an export, in order. Both formats use these names; new columns only
+/// ever go at the end.
+pub const COLUMNS: [&str; 31] = [
+    "time",
+    "at_ms",
+    "event",
+    "break_type",
+    "break_name",
+    "label",
+    "duration_ms",
+    "due_at_ms",
+    "delay_ms",
+    "cause",
+    "microbreak_interval_minutes",
+    "microbreak_duration_seconds",
+    "longbreak_schedule",
+    "longbreak_interval_microbreaks",
+    "longbreak_interval_minutes",
+    "longbreak_duration_minutes",
+    "warning_seconds",
+    "max_postpones_per_break",
+    "strict_microbreaks",
+    "strict_longbreaks",
+    "count_active_time_only",
+    "working_hours_enabled",
+    "pomodoro_mode",
+    "postpone_durations_minutes",
+    "postpone_cooldown_seconds",
+    "escalation_after_dodges",
+    "escalation_steps",
+    "escalation_extra_seconds",
+    "escalation_level",
+    "fixed_breaks",
+    "break_kinds",
+];
+
+/// One history record with the settings in effect when it happened. The settings
+/// columns are empty for records from before settings were recorded. Lists are
+/// space-separated in CSV, and fixed breaks and break kinds JSON-encoded.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportRow {
+    /// Local time with UTC offset (RFC 3339).
+    pub time: String,
+    pub at_ms: u64,
+    pub event: HistoryEvent,
+    /// `Microbreak`, `Longbreak`, `Fixed`, `Custom`, `PomodoroBreak` or `PomodoroLongBreak`.
+    pub break_type: Option<&'static str>,
+    /// Name of the fixed break or break kind.
+    pub break_name: Option<String>,
+    /// Task of the pomodoro.
+    pub label: Option<String>,
+    pub duration_ms: Option<u64>,
+    pub due_at_ms: Option<u64>,
+    pub delay_ms: Option<u64>,
+    pub cause: Option<Interruption>,
+    pub microbreak_interval_minutes: Option<u32>,
+    pub microbreak_duration_seconds: Option<u32>,
+    pub longbreak_schedule: Option<LongbreakSchedule>,
+    pub longbreak_interval_microbreaks: Option<u32>,
+    pub longbreak_interval_minutes: Option<u32>,
+    pub longbreak_duration_minutes: Option<u32>,
+    pub warning_seconds: Option<u32>,
+    pub max_postpones_per_break: Option<u32>,
+    pub strict_microbreaks: Option<bool>,
+    pub strict_longbreaks: Option<bool>,
+    pub count_active_time_only: Option<bool>,
+    pub working_hours_enabled: Option<bool>,
+    pub pomodoro_mode: Option<PomodoroMode>,
+    pub postpone_durations_minutes: Option<Vec<u32>>,
+    pub postpone_cooldown_seconds: Option<u32>,
+    pub escalation_after_dodges: Option<u32>,
+    pub escalation_steps: Option<Vec<EscalationStep>>,
+    pub escalation_extra_seconds: Option<u32>,
+    /// Escalation level a break started at.
+    pub escalation_level: Option<u32>,
+    pub fixed_breaks: Option<Vec<FixedBreak>>,
+    pub break_kinds: Option<Vec<BreakKind>>,
+}
+
+impl ExportRow {
+    fn new<Tz: TimeZone>(tz: &Tz, record: &HistoryRecord, settings: Option<&BreakSettings>) -> Self
+    where
+        Tz::Offset: Display,
+    {
+        let time = tz
+            .timestamp_millis_opt(record.at as i64)
+            .single()
+            .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, false))
+            .unwrap_or_default();
+        let (break_type, break_name) = match &record.break_type {
+            None => (None, None),
+            Some(BreakType::Microbreak) => (Some("Microbreak"), None),
+            Some(BreakType::Longbreak) => (Some("Longbreak"), None),
+            Some(BreakType::Fixed(name)) => (Some("Fixed"), Some(name.clone())),
+            Some(BreakType::Custom(name)) => (Some("Custom"), Some(name.clone())),
+            Some(BreakType::PomodoroBreak) => (Some("PomodoroBreak"), None),
+            Some(BreakType::PomodoroLongBreak) => (Some("PomodoroLongBreak"), None),
+        };
+        Self {
+            time,
+            at_ms: record.at,
+            event: record.event,
+            break_type,
+            break_name,
+            label: record.label.clone(),
+            duration_ms: record.duration_ms,
+            due_at_ms: record.due_at,
+            delay_ms: record.delay_ms,
+            cause: record.cause,
+            microbreak_interval_minutes: settings.map(|s| s.microbreak_interval_minutes),
+            microbreak_duration_seconds: settings.map(|s| s.microbreak_duration_seconds),
+            longbreak_schedule: settings.map(|s| s.longbreak_schedule),
+            longbreak_interval_microbreaks: settings.map(|s| s.longbreak_interval_microbreaks),
+            longbreak_interval_minutes: settings.map(|s| s.longbreak_interval_minutes),
+            longbreak_duration_minutes: settings.map(|s| s.longbreak_duration_minutes),
+            warning_seconds: settings.map(|s| s.warning_seconds),
+            max_postpones_per_break: settings.map(|s| s.max_postpones_per_break),
+            strict_microbreaks: settings.map(|s| s.strict_microbreaks),
+            strict_longbreaks: settings.map(|s| s.strict_longbreaks),
+            count_active_time_only: settings.map(|s| s.count_active_time_only),
+            working_hours_enabled: settings.map(|s| s.working_hours.enabled),
+            pomodoro_mode: settings.map(|s| s.pomodoro.mode),
+            postpone_durations_minutes: settings.map(|s| s.postpone_durations_minutes.clone()),
+            postpone_cooldown_seconds: settings.map(|s| s.postpone_cooldown_seconds),
+            escalation_after_dodges: settings.map(|s| s.escalation_after_dodges),
+            escalation_steps: settings.map(|s| s.escalation_steps.clone()),
+            escalation_extra_seconds: settings.map(|s| s.escalation_extra_seconds),
+            escalation_level: record.escalation_level,
+            fixed_breaks: settings.map(|s| s.fixed_breaks.clone()),
+            break_kinds: settings.map(|s| s.break_kinds.clone()),
+        }
+    }
+
+    /// The row's values in `COLUMNS` order, empty for missing ones.
+    fn csv_fields(&self) -> [String; COLUMNS.len()] {
+        fn field<T: Display>(value: Option<T>) -> String {
+            value.map(|value| value.to_string()).unwrap_or_default()
+        }
+        fn debug<T: std::fmt::Debug>(value: Option<T>) -> String {
+            value.map(|value| format!("{:?}", value)).unwrap_or_default()
+        }
+        fn list<T: std::fmt::Debug>(values: Option<&Vec<T>>) -> String {
+            let values = values.into_iter().flatten().map(|value| format!("{:?}", value));
+            values.collect::<Vec<_>>().join(" ")
+        }
+        fn json<T: Serialize>(value: Option<&T>) -> String {
+            value.and_then(|value| serde_json::to_string(value).ok()).unwrap_or_default()
+        }
+        [
+            self.time.clone(),
+            self.at_ms.to_string(),
+            format!("{:?}", self.event),
+            field(self.break_type),
+            field(self.break_name.as_ref()),
+            field(self.label.as_ref()),
+            field(self.duration_ms),
+            field(self.due_at_ms),
+            field(self.delay_ms),
+            debug(self.cause),
+            field(self.microbreak_interval_minutes),
+            field(self.microbreak_duration_seconds),
+            debug(self.longbreak_schedule),
+            field(self.longbreak_interval_microbreaks),
+            field(self.longbreak_interval_minutes),
+            field(self.longbreak_duration_minutes),
+            field(self.warning_seconds),
+            field(self.max_postpones_per_break),
+            field(self.strict_microbreaks),
+            field(self.strict_longbreaks),
+            field(self.count_active_time_only),
+            field(self.working_hours_enabled),
+            debug(self.pomodoro_mode),
+            list(self.postpone_durations_minutes.as_ref()),
+            field(self.postpone_cooldown_seconds),
+            field(self.escalation_after_dodges),
+            list(self.escalation_steps.as_ref()),
+            field(self.escalation_extra_seconds),
+            field(self.escalation_level),
+            json(self.fixed_breaks.as_ref()),
+            json(self.break_kinds.as_ref()),
+        ]
+    }
+}
+
+/// Rows for the records between the local dates `from` and `to`, both included.
+pub fn rows<Tz: TimeZone>(tz: &Tz, records: &[HistoryRecord], from: NaiveDate, to: NaiveDate) -> Vec<ExportRow>
+where
+    Tz::Offset: Display,
+{
+    let start = local_instant(tz, from, 0).unwrap_or(0);
+    let end = to.succ_opt().and_then(|day| local_instant(tz, day, 0)).unwrap_or(u64::MAX);
+    let mut settings = None;
+    let mut rows = Vec::new();
+    for record in records {
+        if record.event == HistoryEvent::SettingsApplied {
+            settings = record.settings.as_ref();
+        } else if (start..end).contains(&record.at) {
+            rows.push(ExportRow::new(tz, record, settings));
+        }
+    }
+    rows
+}
+
+/// Quote a CSV field if it needs it.
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub fn write_rows<W: Write>(rows: &[ExportRow], format: ExportFormat, mut out: W) -> io::Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            writeln!(out, "{}", COLUMNS.join(","))?;
+            for row in rows {
+                let fields = row.csv_fields().map(|field| csv_escape(&field));
+                writeln!(out, "{}", fields.join(","))?;
+            }
+        }
+        ExportFormat::JsonLines => {
+            for row in rows {
+                serde_json::to_writer(&mut out, row)?;
+                writeln!(out)?;
+            }
+        }
+    }
+    out.flush()
+}
+
+/// Export the history between the local dates `from` and `to` to a new file named
+/// after the range in the downloads folder. Returns where it went.
+pub fn export_history(app: &AppHandle, from: NaiveDate, to: NaiveDate, format: ExportFormat) -> Result<PathBuf, String> {
+    if from > to {
+        return Err(format!("Invalid date range: {} is after {}", from, to));
+    }
+    let dir = app.path().download_dir().map_err(|e| e.to_string())?;
+    let app_state = app.state::<crate::state::AppState>();
+    let records = crate::history::load(&app_state.history_path);
+    let rows = rows(&Local, &records, from, to);
+
+    let name = format!("restie-history-{}-to-{}", from, to);
+    let (path, file) = create_new_file(&dir, &name, format.extension())?;
+    if let Err(e) = write_rows(&rows, format, BufWriter::new(file)) {
+        // Don't leave half an export behind
+        let _ = fs::remove_file(&path);
+        return Err(format!("Failed to write {:?}: {}", path, e));
+    }
+    println!("Exported {} history records to {:?}", rows.len(), path);
+    Ok(path)
+}
+
+/// Create `<name>.<extension>` in `dir`, or `<name> (2).<extension>` and so on if that
+/// is taken. Existing files are never overwritten.
+fn create_new_file(dir: &Path, name: &str, extension: &str) -> Result<(PathBuf, File), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    for n in 1..=MAX_SAME_NAME_EXPORTS {
+        let path = match n {
+            1 => dir.join(format!("{}.{}", name, extension)),
+            n => dir.join(format!("{} ({}).{}", name, n, extension)),
+        };
+        match File::options().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Failed to create {:?}: {}", path, e)),
+        }
+    }
+    Err(format!("Too many exports named {} in {:?}", name, dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use chrono::Utc;
+
+    const MINUTE: u64 = 60 * 1000;
+
+    fn at(day: u32, hour: u32) -> u64 {
+        Utc.with_ymd_and_hms(2024, 6, day, hour, 0, 0).unwrap().timestamp_millis() as u64
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 6, day).unwrap()
+    }
+
+    #[test]
+    fn rows_carry_the_settings_in_effect() {
+        let stricter = BreakSettings {
+            strict_microbreaks: true,
+            ..BreakSettings::default()
+        };
+        let records = vec![
+            HistoryRecord::new(at(2, 9), HistoryEvent::BreakSkipped),
+            HistoryRecord {
+                settings: Some(BreakSettings::default()),
+                ..HistoryRecord::new(at(3, 8), HistoryEvent::SettingsApplied)
+            },
+            HistoryRecord {
+                break_type: Some(BreakType::Custom("Eyes, \"far\"".to_string())),
+                duration_ms: Some(MINUTE),
+                ..HistoryRecord::new(at(3, 9), HistoryEvent::BreakCompleted)
+            },
+            HistoryRecord {
+                settings: Some(stricter),
+                ..HistoryRecord::new(at(3, 12), HistoryEvent::SettingsApplied)
+            },
+            HistoryRecord {
+                escalation_level: Some(1),
+                ..HistoryRecord::new(at(4, 9), HistoryEvent::BreakStarted)
+            },
+            HistoryRecord::new(at(5, 9), HistoryEvent::BreakSkipped),
+        ];
+
+        let rows = rows(&Utc, &records, date(2), date(4));
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].microbreak_interval_minutes, None);
+        assert_eq!(rows[1].time, "2024-06-03T09:00:00+00:00");
+        assert_eq!(rows[1].strict_microbreaks, Some(false));
+        assert_eq!(rows[2].strict_microbreaks, Some(true));
+        assert_eq!(rows[2].escalation_level, Some(1));
+
+        let mut csv = Vec::new();
+        write_rows(&rows[1..2], ExportFormat::Csv, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], COLUMNS.join(","));
+        assert!(lines[1].starts_with(&format!(
+            "2024-06-03T09:00:00+00:00,{},BreakCompleted,Custom,\"Eyes, \"\"far\"\"\",,60000,,,,",
+            at(3, 9)
+        )));
+        assert!(lines[1].ends_with(",false,false,false,false,Off,5 10,60,0,Fullscreen LongerMicrobreaks Strict,20,,[],[]"));
+    }
+
+    #[test]
+    fn json_lines_use_the_csv_columns() {
+        let records = vec![HistoryRecord::new(at(3, 9), HistoryEvent::BreakSkipped)];
+        let mut json = Vec::new();
+        write_rows(&rows(&Utc, &records, date(3), date(3)), ExportFormat::JsonLines, &mut json).unwrap();
+
+        let row: serde_json::Map<String, serde_json::Value> = serde_json::from_slice(&json).unwrap();
+        let mut keys: Vec<&str> = row.keys().map(String::as_str).collect();
+        let mut columns = COLUMNS.to_vec();
+        keys.sort();
+        columns.sort();
+        assert_eq!(keys, columns);
+        assert_eq!(row["event"], "BreakSkipped");
+    }
+
+    #[test]
+    fn exports_never_overwrite_earlier_ones() {
+        let dir = TempDir::new("export");
+        let existing = dir.join("restie-history-2024-06-01-to-2024-06-07.csv");
+        fs::write(&existing, "kept").unwrap();
+
+        let (path, _) = create_new_file(&dir, "restie-history-2024-06-01-to-2024-06-07", "csv").unwrap();
+        assert_eq!(path, dir.join("restie-history-2024-06-01-to-2024-06-07 (2).csv"));
+        let (path, _) = create_new_file(&dir, "restie-history-2024-06-01-to-2024-06-07", "csv").unwrap();
+        assert_eq!(path, dir.join("restie-history-2024-06-01-to-2024-06-07 (3).csv"));
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "kept");
+    }
+}
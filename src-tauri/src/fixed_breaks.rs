@@ -0,0 +1,154 @@
+use crate::clock::{local_date, local_instant, parse_time_of_day};
+use chrono::{Datelike, Days, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+
+const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// A named break at set local times, e.g. lunch, on top of the interval breaks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FixedBreak {
+    pub name: String,
+    /// When it happens: `"[days] HH:MM"`, where days is `daily`, `weekdays`, `weekends`
+    /// or a list like `Mon,Wed,Fri` or `Mon-Thu`; without days it happens daily.
+    pub at: String,
+    pub duration_minutes: u32,
+    /// Can't be skipped or postponed.
+    pub strict: bool,
+}
+
+/// A parsed `FixedBreak::at`.
+#[derive(Debug, PartialEq, Eq)]
+struct FixedTime {
+    /// Monday first.
+    days: [bool; 7],
+    minutes: u32,
+}
+
+fn parse_weekday(day: &str) -> Option<usize> {
+    WEEKDAYS.iter().position(|name| name.eq_ignore_ascii_case(day))
+}
+
+fn parse_days(days: &str) -> Result<[bool; 7], String> {
+    match days.to_ascii_lowercase().as_str() {
+        "daily" => return Ok([true; 7]),
+        "weekdays" => return Ok([true, true, true, true, true, false, false]),
+        "weekends" => return Ok([false, false, false, false, false, true, true]),
+        _ => {}
+    }
+    let mut selected = [false; 7];
+    for part in days.split(',') {
+        let part = part.trim();
+        let (first, last) = part.split_once('-').unwrap_or((part, part));
+        let (Some(first), Some(last)) = (parse_weekday(first.trim()), parse_weekday(last.trim())) else {
+            return Err(format!("Unknown day `{}`: use Mon, Tue, ... Sun", part));
+        };
+        if first > last {
+            return Err(format!("Day range `{}` must run from Monday towards Sunday", part));
+        }
+        selected[first..=last].iter_mut().for_each(|day| *day = true);
+    }
+    Ok(selected)
+}
+
+fn parse_at(at: &str) -> Result<FixedTime, String> {
+    let (days, time) = match at.trim().rsplit_once(char::is_whitespace) {
+        Some((days, time)) => (parse_days(days.trim())?, time),
+        None => ([true; 7], at.trim()),
+    };
+    match parse_time_of_day(time) {
+        Some(minutes) if minutes < 24 * 60 => Ok(FixedTime { days, minutes }),
+        _ => Err(format!("Invalid time `{}`: use HH:MM", time)),
+    }
+}
+
+impl FixedBreak {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Fixed breaks need a name".to_string());
+        }
+        if self.duration_minutes < 1 || self.duration_minutes > 120 {
+            return Err(format!("Invalid duration for {}: must be 1-120 minutes", self.name));
+        }
+        parse_at(&self.at).map_err(|e| format!("Invalid time for {}: {}", self.name, e))?;
+        Ok(())
+    }
+
+    /// First time after `after_ms` this break happens, in `tz`.
+    fn next_occurrence_in<Tz: TimeZone>(&self, tz: &Tz, after_ms: u64) -> Option<u64> {
+        let fixed = parse_at(&self.at).ok()?;
+        let today = local_date(tz, after_ms)?;
+        // Today's time may have passed, so a weekly break can be eight days out
+        (0..=7)
+            .filter_map(|offset| today.checked_add_days(Days::new(offset)))
+            .filter(|date| fixed.days[date.weekday().num_days_from_monday() as usize])
+            .filter_map(|date| local_instant(tz, date, fixed.minutes))
+            .find(|&at| at > after_ms)
+    }
+}
+
+/// The fixed break that comes next after `now_ms` and when; the earliest-listed one
+/// wins a tie.
+pub fn next_fixed_break(breaks: &[FixedBreak], now_ms: u64) -> Option<(&FixedBreak, u64)> {
+    next_fixed_break_in(&Local, breaks, now_ms)
+}
+
+fn next_fixed_break_in<'a, Tz: TimeZone>(tz: &Tz, breaks: &'a [FixedBreak], now_ms: u64) -> Option<(&'a FixedBreak, u64)> {
+    breaks
+        .iter()
+        .filter_map(|fixed| Some((fixed, fixed.next_occurrence_in(tz, now_ms)?)))
+        .min_by_key(|&(_, at)| at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn at(date: (i32, u32, u32), time: (u32, u32)) -> u64 {
+        Utc.with_ymd_and_hms(date.0, date.1, date.2, time.0, time.1, 0).unwrap().timestamp_millis() as u64
+    }
+
+    fn fixed(name: &str, at: &str) -> FixedBreak {
+        FixedBreak {
+            name: name.to_string(),
+            at: at.to_string(),
+            duration_minutes: 30,
+            strict: false,
+        }
+    }
+
+    #[test]
+    fn parses_weekday_and_time_rules() {
+        assert_eq!(parse_at("12:30"), Ok(FixedTime { days: [true; 7], minutes: 12 * 60 + 30 }));
+        assert_eq!(
+            parse_at("Mon-Wed,fri 15:00"),
+            Ok(FixedTime { days: [true, true, true, false, true, false, false], minutes: 15 * 60 })
+        );
+        assert_eq!(parse_at("weekends 10:00").unwrap().days, [false, false, false, false, false, true, true]);
+        assert!(parse_at("Fri-Mon 10:00").is_err());
+        assert!(parse_at("Someday 10:00").is_err());
+        assert!(parse_at("24:00").is_err());
+        assert!(parse_at("weekdays").is_err());
+    }
+
+    #[test]
+    fn next_occurrence_follows_the_weekdays() {
+        let lunch = fixed("Lunch", "weekdays 12:30");
+        // 2024-06-07 is a Friday
+        assert_eq!(lunch.next_occurrence_in(&Utc, at((2024, 6, 7), (9, 0))), Some(at((2024, 6, 7), (12, 30))));
+        assert_eq!(lunch.next_occurrence_in(&Utc, at((2024, 6, 7), (12, 30))), Some(at((2024, 6, 10), (12, 30))));
+
+        let weekly = fixed("Review", "Fri 16:00");
+        assert_eq!(weekly.next_occurrence_in(&Utc, at((2024, 6, 7), (17, 0))), Some(at((2024, 6, 14), (16, 0))));
+    }
+
+    #[test]
+    fn earliest_fixed_break_comes_next() {
+        let breaks = vec![fixed("Lunch", "12:30"), fixed("Stretch", "15:00")];
+        let next = |now| next_fixed_break_in(&Utc, &breaks, now).map(|(fixed, at)| (fixed.name.as_str(), at));
+
+        assert_eq!(next(at((2024, 6, 3), (9, 0))), Some(("Lunch", at((2024, 6, 3), (12, 30)))));
+        assert_eq!(next(at((2024, 6, 3), (13, 0))), Some(("Stretch", at((2024, 6, 3), (15, 0)))));
+        assert_eq!(next(at((2024, 6, 3), (16, 0))), Some(("Lunch", at((2024, 6, 4), (12, 30)))));
+    }
+}
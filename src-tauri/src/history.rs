@@ -0,0 +1,421 @@
+use crate::clock::{local_date, local_instant};
+use crate::models::{BreakSettings, BreakType};
+use chrono::{Datelike, Days, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// What happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HistoryEvent {
+    /// The next interval or fixed break was scheduled for `due_at`.
+    BreakScheduled,
+    /// The break was announced ahead of time.
+    BreakWarned,
+    /// The break came on screen, `delay_ms` after it first came due.
+    BreakStarted,
+    /// The break ran its course, lasting `duration_ms`.
+    BreakCompleted,
+    BreakSkipped,
+    /// The break was pushed back to `due_at`.
+    BreakPostponed,
+    /// Time away (`cause`) of `duration_ms` counted as the break.
+    BreakInterrupted,
+    PomodoroStarted,
+    PomodoroCompleted,
+    PomodoroInterrupted,
+    PomodoroVoided,
+    /// `settings` are in effect from here on.
+    SettingsApplied,
+}
+
+/// Why the user was away when time away counted as a break.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Interruption {
+    Idle,
+    Suspend,
+}
+
+/// One line of the history file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    /// Unix ms.
+    pub at: u64,
+    pub event: HistoryEvent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub break_type: Option<BreakType>,
+    /// Task the pomodoro was for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// How long the break, time away or pomodoro lasted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// When the break is (now) due, Unix ms.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cause: Option<Interruption>,
+    /// Escalation level the break started at.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalation_level: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<BreakSettings>,
+}
+
+impl HistoryRecord {
+    pub fn new(at: u64, event: HistoryEvent) -> Self {
+        Self {
+            at,
+            event,
+            break_type: None,
+            label: None,
+            duration_ms: None,
+            due_at: None,
+            delay_ms: None,
+            cause: None,
+            escalation_level: None,
+            settings: None,
+        }
+    }
+
+    /// A break the user actually had, on screen or away from the computer, as its
+    /// start and end.
+    pub fn rest(&self) -> Option<(u64, u64)> {
+        match self.event {
+            HistoryEvent::BreakCompleted | HistoryEvent::BreakInterrupted => {
+                Some((self.at.saturating_sub(self.duration_ms.unwrap_or(0)), self.at))
+            }
+            _ => None,
+        }
+    }
+}
+
+pub fn history_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(HISTORY_FILE)
+}
+
+/// Append `record` as one JSON line. Records are never rewritten, so a crash can
+/// at worst cut off the last line; the record then starts on a line of its own.
+pub fn append(path: &Path, record: &HistoryRecord) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    }
+    let line = serde_json::to_string(record).map_err(|e| format!("Failed to serialize history record: {}", e))?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+    let separator = if ends_mid_line(&mut file).map_err(|e| format!("Failed to read {:?}: {}", path, e))? {
+        "\n"
+    } else {
+        ""
+    };
+    // One write, so a crash can't split the separator from the record
+    file.write_all(format!("{}{}\n", separator, line).as_bytes())
+        .map_err(|e| format!("Failed to write {:?}: {}", path, e))
+}
+
+/// Whether `file` is non-empty and doesn't end in a newline.
+fn ends_mid_line(file: &mut File) -> std::io::Result<bool> {
+    if file.metadata()?.len() == 0 {
+        return Ok(false);
+    }
+    let mut last = [0; 1];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last)?;
+    Ok(last[0] != b'\n')
+}
+
+/// Every readable record in `path`, oldest first. Lines that don't parse (a cut-off
+/// write) are skipped.
+pub fn load(path: &Path) -> Vec<HistoryRecord> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+        Err(e) => {
+            println!("Warning: could not read break history {:?}: {}", path, e);
+            return Vec::new();
+        }
+    };
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// The settings last recorded in `records`.
+pub fn last_settings(records: &[HistoryRecord]) -> Option<&BreakSettings> {
+    records.iter().rev().find_map(|record| record.settings.as_ref())
+}
+
+/// Task labels of the latest pomodoros, newest first, without repeats.
+pub fn recent_labels(records: &[HistoryRecord], max: usize) -> Vec<String> {
+    let mut labels: Vec<String> = Vec::new();
+    for label in records.iter().rev().filter_map(|record| record.label.as_ref()) {
+        if labels.len() == max {
+            break;
+        }
+        if !labels.contains(label) {
+            labels.push(label.clone());
+        }
+    }
+    labels
+}
+
+/// Length of the periods `summaries` reports on. Weeks start on Monday.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SummaryPeriod {
+    Day,
+    Week,
+}
+
+/// How breaks went over one period.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HistorySummary {
+    /// Start and end of the period, Unix ms.
+    pub from: u64,
+    pub to: u64,
+    /// Breaks taken on screen or by being away.
+    pub breaks_taken: u32,
+    pub breaks_skipped: u32,
+    pub postpones: u32,
+    /// Share of breaks taken rather than skipped; `None` without any breaks.
+    pub compliance_rate: Option<f64>,
+    pub breaks_started: u32,
+    /// How long breaks came on screen after they were due, on average.
+    pub average_delay_ms: Option<u64>,
+    /// Longest time worked between two breaks, within a day.
+    pub longest_stretch_ms: u64,
+    pub pomodoros_completed: u32,
+}
+
+impl HistorySummary {
+    /// Add up the summaries of consecutive periods.
+    fn combine(from: u64, to: u64, parts: &[HistorySummary]) -> HistorySummary {
+        let mut total = HistorySummary { from, to, ..HistorySummary::default() };
+        let mut delay_total = 0;
+        for part in parts {
+            total.breaks_taken += part.breaks_taken;
+            total.breaks_skipped += part.breaks_skipped;
+            total.postpones += part.postpones;
+            total.breaks_started += part.breaks_started;
+            delay_total += part.average_delay_ms.unwrap_or(0) * part.breaks_started as u64;
+            total.longest_stretch_ms = total.longest_stretch_ms.max(part.longest_stretch_ms);
+            total.pomodoros_completed += part.pomodoros_completed;
+        }
+        total.compliance_rate = compliance_rate(total.breaks_taken, total.breaks_skipped);
+        total.average_delay_ms = (total.breaks_started > 0).then(|| delay_total / total.breaks_started as u64);
+        total
+    }
+}
+
+fn compliance_rate(taken: u32, skipped: u32) -> Option<f64> {
+    let due = taken + skipped;
+    (due > 0).then(|| taken as f64 / due as f64)
+}
+
+/// Summarize the records between `from` and `to` (Unix ms). Stretches are measured
+/// from the first record, or the end of a break, to the start of the next break or the
+/// last record, so only pass a period the user could have worked through, like a day.
+pub fn summarize(records: &[HistoryRecord], from: u64, to: u64) -> HistorySummary {
+    let mut summary = HistorySummary { from, to, ..HistorySummary::default() };
+    let mut delay_total = 0;
+    let mut working_since: Option<u64> = None;
+    let mut last_at = None;
+
+    for record in records.iter().filter(|record| (from..to).contains(&record.at)) {
+        match record.event {
+            HistoryEvent::BreakCompleted | HistoryEvent::BreakInterrupted => summary.breaks_taken += 1,
+            HistoryEvent::BreakSkipped => summary.breaks_skipped += 1,
+            HistoryEvent::BreakPostponed => summary.postpones += 1,
+            HistoryEvent::BreakStarted => {
+                summary.breaks_started += 1;
+                delay_total += record.delay_ms.unwrap_or(0);
+            }
+            HistoryEvent::PomodoroCompleted => summary.pomodoros_completed += 1,
+            _ => {}
+        }
+        match record.rest() {
+            Some((rest_start, rest_end)) => {
+                if let Some(start) = working_since {
+                    summary.longest_stretch_ms = summary.longest_stretch_ms.max(rest_start.saturating_sub(start));
+                }
+                working_since = Some(rest_end);
+            }
+            None => {
+                working_since.get_or_insert(record.at);
+            }
+        }
+        last_at = Some(record.at);
+    }
+    if let (Some(start), Some(end)) = (working_since, last_at) {
+        summary.longest_stretch_ms = summary.longest_stretch_ms.max(end.saturating_sub(start));
+    }
+
+    summary.compliance_rate = compliance_rate(summary.breaks_taken, summary.breaks_skipped);
+    summary.average_delay_ms = (summary.breaks_started > 0).then(|| delay_total / summary.breaks_started as u64);
+    summary
+}
+
+/// Summaries of the last `count` local days or weeks up to `now_ms`, oldest first; the
+/// last one is the day or week in progress.
+pub fn summaries(records: &[HistoryRecord], period: SummaryPeriod, count: u32, now_ms: u64) -> Vec<HistorySummary> {
+    summaries_in(&Local, records, period, count, now_ms)
+}
+
+fn summaries_in<Tz: TimeZone>(
+    tz: &Tz,
+    records: &[HistoryRecord],
+    period: SummaryPeriod,
+    count: u32,
+    now_ms: u64,
+) -> Vec<HistorySummary> {
+    let Some(today) = local_date(tz, now_ms) else {
+        return Vec::new();
+    };
+    let (first, days) = match period {
+        SummaryPeriod::Day => (today, 1),
+        SummaryPeriod::Week => (week_start(today), 7),
+    };
+    (0..count as u64)
+        .rev()
+        .filter_map(|periods_ago| {
+            let start = first.checked_sub_days(Days::new(periods_ago * days))?;
+            days_summary(tz, records, start, days)
+        })
+        .collect()
+}
+
+/// The Monday of the week `date` is in.
+pub fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Days::new(date.weekday().num_days_from_monday() as u64)
+}
+
+/// Summary of the `days` local days from `start` on.
+pub fn days_summary<Tz: TimeZone>(tz: &Tz, records: &[HistoryRecord], start: NaiveDate, days: u64) -> Option<HistorySummary> {
+    // Day by day, so nights don't count as stretches without a break
+    let daily: Vec<HistorySummary> = (0..days)
+        .filter_map(|offset| {
+            let date = start.checked_add_days(Days::new(offset))?;
+            let from = local_instant(tz, date, 0)?;
+            let to = local_instant(tz, date.succ_opt()?, 0)?;
+            Some(summarize(records, from, to))
+        })
+        .collect();
+    Some(HistorySummary::combine(daily.first()?.from, daily.last()?.to, &daily))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use crate::test_support::TempDir;
+
+    const MINUTE: u64 = 60 * 1000;
+
+    fn at(date: (i32, u32, u32), time: (u32, u32)) -> u64 {
+        Utc.with_ymd_and_hms(date.0, date.1, date.2, time.0, time.1, 0).unwrap().timestamp_millis() as u64
+    }
+
+    fn record(at: u64, event: HistoryEvent) -> HistoryRecord {
+        HistoryRecord::new(at, event)
+    }
+
+    fn rest(end: u64, event: HistoryEvent, duration_ms: u64) -> HistoryRecord {
+        HistoryRecord {
+            duration_ms: Some(duration_ms),
+            ..HistoryRecord::new(end, event)
+        }
+    }
+
+    #[test]
+    fn records_survive_a_cut_off_last_line() {
+        let dir = TempDir::new("history");
+        let path = history_path(&dir);
+
+        let record = HistoryRecord {
+            label: Some("Write report".to_string()),
+            duration_ms: Some(25 * MINUTE),
+            ..HistoryRecord::new(1_700_000_000_000, HistoryEvent::PomodoroCompleted)
+        };
+        append(&path, &record).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "{{\"at\":17000").unwrap();
+        assert_eq!(load(&path), vec![record.clone()]);
+
+        let next = HistoryRecord::new(1_700_000_100_000, HistoryEvent::BreakSkipped);
+        append(&path, &next).unwrap();
+        assert_eq!(load(&path), vec![record, next]);
+    }
+
+    #[test]
+    fn day_summary_counts_compliance_delay_and_stretches() {
+        let day = |time| at((2024, 6, 3), time);
+        let records = vec![
+            record(day((9, 0)), HistoryEvent::BreakScheduled),
+            HistoryRecord {
+                delay_ms: Some(2 * MINUTE),
+                ..record(day((9, 22)), HistoryEvent::BreakStarted)
+            },
+            rest(day((9, 23)), HistoryEvent::BreakCompleted, MINUTE),
+            record(day((9, 40)), HistoryEvent::BreakPostponed),
+            record(day((9, 45)), HistoryEvent::BreakSkipped),
+            // Away from 11:00 to 11:30
+            rest(day((11, 30)), HistoryEvent::BreakInterrupted, 30 * MINUTE),
+            record(day((12, 0)), HistoryEvent::BreakStarted),
+            rest(day((12, 1)), HistoryEvent::BreakCompleted, MINUTE),
+            record(day((12, 30)), HistoryEvent::BreakScheduled),
+            // Another day
+            record(at((2024, 6, 4), (9, 0)), HistoryEvent::BreakSkipped),
+        ];
+
+        let summary = summarize(&records, day((0, 0)), at((2024, 6, 4), (0, 0)));
+        assert_eq!(summary.breaks_taken, 3);
+        assert_eq!(summary.breaks_skipped, 1);
+        assert_eq!(summary.postpones, 1);
+        assert_eq!(summary.compliance_rate, Some(0.75));
+        assert_eq!(summary.average_delay_ms, Some(MINUTE));
+        // From the end of the first break until going away
+        assert_eq!(summary.longest_stretch_ms, 97 * MINUTE);
+    }
+
+    #[test]
+    fn week_summary_adds_up_its_days() {
+        let records = vec![
+            HistoryRecord {
+                delay_ms: Some(4 * MINUTE),
+                ..record(at((2024, 6, 3), (10, 0)), HistoryEvent::BreakStarted)
+            },
+            rest(at((2024, 6, 3), (10, 5)), HistoryEvent::BreakCompleted, 5 * MINUTE),
+            record(at((2024, 6, 5), (10, 0)), HistoryEvent::BreakStarted),
+            rest(at((2024, 6, 5), (10, 5)), HistoryEvent::BreakCompleted, 5 * MINUTE),
+            record(at((2024, 6, 5), (11, 0)), HistoryEvent::BreakSkipped),
+            // The week before
+            record(at((2024, 5, 31), (10, 0)), HistoryEvent::BreakSkipped),
+        ];
+        // Thursday of that week
+        let now = at((2024, 6, 6), (12, 0));
+
+        let weeks = summaries_in(&Utc, &records, SummaryPeriod::Week, 2, now);
+        assert_eq!(weeks.len(), 2);
+        assert_eq!((weeks[1].from, weeks[1].to), (at((2024, 6, 3), (0, 0)), at((2024, 6, 10), (0, 0))));
+        assert_eq!(weeks[1].breaks_taken, 2);
+        assert_eq!(weeks[1].breaks_skipped, 1);
+        assert_eq!(weeks[1].average_delay_ms, Some(2 * MINUTE));
+        // Nights between the days don't count
+        assert_eq!(weeks[1].longest_stretch_ms, 55 * MINUTE);
+        assert_eq!(weeks[0].breaks_skipped, 1);
+        assert_eq!(weeks[0].compliance_rate, Some(0.0));
+
+        let days = summaries_in(&Utc, &records, SummaryPeriod::Day, 3, now);
+        assert_eq!(days.iter().map(|day| day.breaks_taken).collect::<Vec<_>>(), vec![0, 1, 0]);
+    }
+}
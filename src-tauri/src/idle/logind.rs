@@ -0,0 +1,49 @@
+use super::IdleSource;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zbus::blocking::{proxy::Builder, Connection, Proxy};
+use zbus::proxy::CacheProperties;
+
+/// Idle state from systemd-logind's `IdleHint` on the current session.
+///
+/// The desktop sets the hint after its own idle delay, so short idle periods are
+/// reported as 0; it is only used when no finer-grained backend is available.
+pub struct LogindIdle {
+    session: Proxy<'static>,
+}
+
+impl LogindIdle {
+    pub fn connect() -> Result<Self, String> {
+        let connection = Connection::system().map_err(|e| e.to_string())?;
+        let session = Builder::<Proxy>::new(&connection)
+            .destination("org.freedesktop.login1")
+            .and_then(|b| b.path("/org/freedesktop/login1/session/auto"))
+            .and_then(|b| b.interface("org.freedesktop.login1.Session"))
+            .map_err(|e| e.to_string())?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        // Fail now rather than on every poll if the session can't be queried
+        session.get_property::<bool>("IdleHint").map_err(|e| e.to_string())?;
+
+        log::info!("Idle: using logind IdleHint");
+        Ok(Self { session })
+    }
+}
+
+impl IdleSource for LogindIdle {
+    fn idle_ms(&self) -> Option<u64> {
+        let idle: bool = self.session.get_property("IdleHint").ok()?;
+        if !idle {
+            return Some(0);
+        }
+        // Microseconds since the epoch at which the session went idle
+        let since_us: u64 = self.session.get_property("IdleSinceHint").ok()?;
+        let now_us = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_micros() as u64;
+        Some(now_us.saturating_sub(since_us) / 1000)
+    }
+
+    fn name(&self) -> &'static str {
+        "logind"
+    }
+}
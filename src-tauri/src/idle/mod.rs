@@ -0,0 +1,80 @@
+#[cfg(target_os = "linux")]
+mod logind;
+#[cfg(target_os = "linux")]
+mod wayland;
+#[cfg(target_os = "linux")]
+mod x11;
+
+use std::sync::Arc;
+
+/// Reports how long the user has been away from the keyboard and mouse.
+pub trait IdleSource: Send + Sync {
+    /// Milliseconds since the last user input, or `None` if that can't be told
+    /// right now (e.g. the backend lost its connection).
+    fn idle_ms(&self) -> Option<u64>;
+
+    /// Backend name for logs.
+    fn name(&self) -> &'static str;
+}
+
+/// Pick the best idle backend for this session, or `None` if the platform has none.
+///
+/// On Wayland only the compositor protocol will do: the X11 screensaver extension
+/// under XWayland only sees input sent to X clients, so typing in native windows
+/// would look like being away. logind's IdleHint is the last resort: it only flips
+/// after the desktop's own idle delay.
+pub fn detect() -> Option<Arc<dyn IdleSource>> {
+    #[cfg(target_os = "linux")]
+    {
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            match wayland::WaylandIdle::connect() {
+                Ok(source) => return Some(Arc::new(source)),
+                Err(e) => log::warn!("Idle: Wayland ext-idle-notify unavailable: {}", e),
+            }
+        } else if std::env::var_os("DISPLAY").is_some() {
+            match x11::X11Idle::connect() {
+                Ok(source) => return Some(Arc::new(source)),
+                Err(e) => log::warn!("Idle: X11 screensaver extension unavailable: {}", e),
+            }
+        }
+        match logind::LogindIdle::connect() {
+            Ok(source) => return Some(Arc::new(source)),
+            Err(e) => log::warn!("Idle: logind IdleHint unavailable: {}", e),
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+pub use fake::FakeIdleSource;
+
+#[cfg(test)]
+mod fake {
+    use super::IdleSource;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// Idle source whose idle time is set by the test. Clones share the value.
+    #[derive(Clone, Default)]
+    pub struct FakeIdleSource {
+        idle_ms: Arc<AtomicU64>,
+    }
+
+    impl FakeIdleSource {
+        pub fn set_idle(&self, idle: Duration) {
+            self.idle_ms.store(idle.as_millis() as u64, Ordering::SeqCst);
+        }
+    }
+
+    impl IdleSource for FakeIdleSource {
+        fn idle_ms(&self) -> Option<u64> {
+            Some(self.idle_ms.load(Ordering::SeqCst))
+        }
+
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+    }
+}
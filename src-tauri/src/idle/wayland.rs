@@ -0,0 +1,329 @@
+//! Idle notifications from the compositor via the `ext-idle-notify-v1` protocol.
+//!
+//! Only a handful of messages are needed, so this speaks the Wayland wire format
+//! directly over the compositor socket instead of pulling in a client library.
+
+use super::IdleSource;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Inactivity after which the compositor reports the seat as idle. Idle time below
+/// this is reported as 0.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Object ids we allocate, in the order they are created.
+const DISPLAY_ID: u32 = 1;
+const REGISTRY_ID: u32 = 2;
+const SYNC_CALLBACK_ID: u32 = 3;
+const SEAT_ID: u32 = 4;
+const NOTIFIER_ID: u32 = 5;
+const NOTIFICATION_ID: u32 = 6;
+
+// Opcodes
+const DISPLAY_SYNC: u16 = 0;
+const DISPLAY_GET_REGISTRY: u16 = 1;
+const DISPLAY_ERROR_EVENT: u16 = 0;
+const REGISTRY_BIND: u16 = 0;
+const REGISTRY_GLOBAL_EVENT: u16 = 0;
+const CALLBACK_DONE_EVENT: u16 = 0;
+const NOTIFIER_GET_IDLE_NOTIFICATION: u16 = 1;
+const NOTIFICATION_IDLED_EVENT: u16 = 0;
+const NOTIFICATION_RESUMED_EVENT: u16 = 1;
+
+pub struct WaylandIdle {
+    state: Arc<Mutex<IdleState>>,
+}
+
+#[derive(Default)]
+struct IdleState {
+    /// When the user's last input happened, while the compositor reports idle.
+    idle_since: Option<Instant>,
+    disconnected: bool,
+}
+
+impl WaylandIdle {
+    pub fn connect() -> Result<Self, String> {
+        let stream = UnixStream::connect(socket_path()?).map_err(|e| e.to_string())?;
+        Self::handshake(stream)
+    }
+
+    /// Find the seat and idle notifier globals and subscribe to idle notifications,
+    /// then hand the connection to a thread that tracks idled/resumed events.
+    fn handshake(stream: UnixStream) -> Result<Self, String> {
+        let mut wire = Wire { stream };
+
+        wire.send(DISPLAY_ID, DISPLAY_GET_REGISTRY, &Args::new().uint(REGISTRY_ID))?;
+        wire.send(DISPLAY_ID, DISPLAY_SYNC, &Args::new().uint(SYNC_CALLBACK_ID))?;
+
+        // Collect globals until the sync callback says the registry burst is over
+        let (mut seat, mut notifier) = (None, None);
+        loop {
+            let message = wire.receive()?;
+            match (message.object, message.opcode) {
+                (REGISTRY_ID, REGISTRY_GLOBAL_EVENT) => {
+                    let mut body = message.reader();
+                    let name = body.uint()?;
+                    match body.string()?.as_str() {
+                        "wl_seat" if seat.is_none() => seat = Some(name),
+                        "ext_idle_notifier_v1" => notifier = Some(name),
+                        _ => {}
+                    }
+                }
+                (SYNC_CALLBACK_ID, CALLBACK_DONE_EVENT) => break,
+                (DISPLAY_ID, DISPLAY_ERROR_EVENT) => return Err(message.display_error()),
+                _ => {}
+            }
+        }
+        let seat = seat.ok_or("compositor has no wl_seat")?;
+        let notifier = notifier.ok_or("compositor does not support ext-idle-notify-v1")?;
+
+        wire.send(REGISTRY_ID, REGISTRY_BIND, &Args::new().uint(seat).string("wl_seat").uint(1).uint(SEAT_ID))?;
+        wire.send(
+            REGISTRY_ID,
+            REGISTRY_BIND,
+            &Args::new().uint(notifier).string("ext_idle_notifier_v1").uint(1).uint(NOTIFIER_ID),
+        )?;
+        wire.send(
+            NOTIFIER_ID,
+            NOTIFIER_GET_IDLE_NOTIFICATION,
+            &Args::new().uint(NOTIFICATION_ID).uint(IDLE_TIMEOUT.as_millis() as u32).uint(SEAT_ID),
+        )?;
+
+        let state = Arc::new(Mutex::new(IdleState::default()));
+        let thread_state = state.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = listen(wire, &thread_state) {
+                log::warn!("Idle: Wayland connection lost: {}", e);
+            }
+            thread_state.lock().unwrap().disconnected = true;
+        });
+
+        log::info!("Idle: using Wayland ext-idle-notify");
+        Ok(Self { state })
+    }
+}
+
+impl IdleSource for WaylandIdle {
+    fn idle_ms(&self) -> Option<u64> {
+        let state = self.state.lock().unwrap();
+        if state.disconnected {
+            return None;
+        }
+        Some(state.idle_since.map_or(0, |since| since.elapsed().as_millis() as u64))
+    }
+
+    fn name(&self) -> &'static str {
+        "wayland"
+    }
+}
+
+fn listen(mut wire: Wire, state: &Mutex<IdleState>) -> Result<(), String> {
+    loop {
+        let message = wire.receive()?;
+        match (message.object, message.opcode) {
+            (NOTIFICATION_ID, NOTIFICATION_IDLED_EVENT) => {
+                // The event arrives once the timeout has already elapsed
+                state.lock().unwrap().idle_since = Instant::now().checked_sub(IDLE_TIMEOUT);
+            }
+            (NOTIFICATION_ID, NOTIFICATION_RESUMED_EVENT) => {
+                state.lock().unwrap().idle_since = None;
+            }
+            (DISPLAY_ID, DISPLAY_ERROR_EVENT) => return Err(message.display_error()),
+            _ => {}
+        }
+    }
+}
+
+fn socket_path() -> Result<PathBuf, String> {
+    let display = std::env::var_os("WAYLAND_DISPLAY").unwrap_or_else(|| "wayland-0".into());
+    let display = PathBuf::from(display);
+    if display.is_absolute() {
+        return Ok(display);
+    }
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").ok_or("XDG_RUNTIME_DIR is not set")?;
+    Ok(PathBuf::from(runtime_dir).join(display))
+}
+
+struct Wire {
+    stream: UnixStream,
+}
+
+struct Message {
+    object: u32,
+    opcode: u16,
+    body: Vec<u8>,
+}
+
+impl Wire {
+    fn send(&mut self, object: u32, opcode: u16, args: &Args) -> Result<(), String> {
+        let size = 8 + args.0.len() as u32;
+        let mut bytes = Vec::with_capacity(size as usize);
+        bytes.extend_from_slice(&object.to_ne_bytes());
+        bytes.extend_from_slice(&((size << 16) | opcode as u32).to_ne_bytes());
+        bytes.extend_from_slice(&args.0);
+        self.stream.write_all(&bytes).map_err(|e| e.to_string())
+    }
+
+    fn receive(&mut self) -> Result<Message, String> {
+        let mut header = [0u8; 8];
+        self.stream.read_exact(&mut header).map_err(|e| e.to_string())?;
+        let object = u32::from_ne_bytes(header[0..4].try_into().unwrap());
+        let size_and_opcode = u32::from_ne_bytes(header[4..8].try_into().unwrap());
+        let size = (size_and_opcode >> 16) as usize;
+        if size < 8 {
+            return Err(format!("malformed message of {} bytes", size));
+        }
+        let mut body = vec![0u8; size - 8];
+        self.stream.read_exact(&mut body).map_err(|e| e.to_string())?;
+        Ok(Message {
+            object,
+            opcode: (size_and_opcode & 0xffff) as u16,
+            body,
+        })
+    }
+}
+
+impl Message {
+    fn reader(&self) -> Reader<'_> {
+        Reader { bytes: &self.body }
+    }
+
+    fn display_error(&self) -> String {
+        let mut body = self.reader();
+        let _object = body.uint();
+        let code = body.uint().unwrap_or_default();
+        let text = body.string().unwrap_or_default();
+        format!("protocol error {}: {}", code, text)
+    }
+}
+
+/// Request arguments, encoded as they are appended.
+struct Args(Vec<u8>);
+
+impl Args {
+    fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    fn uint(mut self, value: u32) -> Self {
+        self.0.extend_from_slice(&value.to_ne_bytes());
+        self
+    }
+
+    /// Length (including the NUL terminator), bytes, NUL, padding to 32 bits.
+    fn string(mut self, value: &str) -> Self {
+        self.0.extend_from_slice(&(value.len() as u32 + 1).to_ne_bytes());
+        self.0.extend_from_slice(value.as_bytes());
+        self.0.push(0);
+        while !self.0.len().is_multiple_of(4) {
+            self.0.push(0);
+        }
+        self
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn uint(&mut self) -> Result<u32, String> {
+        if self.bytes.len() < 4 {
+            return Err("truncated message".to_string());
+        }
+        let (value, rest) = self.bytes.split_at(4);
+        self.bytes = rest;
+        Ok(u32::from_ne_bytes(value.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.uint()? as usize;
+        let padded = len.div_ceil(4) * 4;
+        if self.bytes.len() < padded || len == 0 {
+            return Err("truncated string".to_string());
+        }
+        let (value, rest) = self.bytes.split_at(padded);
+        self.bytes = rest;
+        // Drop the NUL terminator
+        Ok(String::from_utf8_lossy(&value[..len - 1]).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Plays the compositor side of the handshake on the other end of a socket pair.
+    fn compositor(stream: UnixStream, with_notifier: bool) -> Wire {
+        let mut wire = Wire { stream };
+        assert_eq!(wire.receive().unwrap().opcode, DISPLAY_GET_REGISTRY);
+        assert_eq!(wire.receive().unwrap().opcode, DISPLAY_SYNC);
+
+        let global = |name: u32, interface: &str| Args::new().uint(name).string(interface).uint(1);
+        wire.send(REGISTRY_ID, REGISTRY_GLOBAL_EVENT, &global(1, "wl_compositor")).unwrap();
+        wire.send(REGISTRY_ID, REGISTRY_GLOBAL_EVENT, &global(2, "wl_seat")).unwrap();
+        if with_notifier {
+            wire.send(REGISTRY_ID, REGISTRY_GLOBAL_EVENT, &global(3, "ext_idle_notifier_v1")).unwrap();
+        }
+        wire.send(SYNC_CALLBACK_ID, CALLBACK_DONE_EVENT, &Args::new().uint(0)).unwrap();
+        wire
+    }
+
+    #[test]
+    fn tracks_idled_and_resumed_events() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let server = std::thread::spawn(move || {
+            let mut wire = compositor(server, true);
+            let seat_bind = wire.receive().unwrap();
+            let mut body = seat_bind.reader();
+            assert_eq!((body.uint().unwrap(), body.string().unwrap()), (2, "wl_seat".to_string()));
+            let notifier_bind = wire.receive().unwrap();
+            assert_eq!(notifier_bind.reader().uint().unwrap(), 3);
+            let subscribe = wire.receive().unwrap();
+            assert_eq!((subscribe.object, subscribe.opcode), (NOTIFIER_ID, NOTIFIER_GET_IDLE_NOTIFICATION));
+            wire
+        });
+
+        let idle = WaylandIdle::handshake(client).unwrap();
+        let mut wire = server.join().unwrap();
+        assert_eq!(idle.idle_ms(), Some(0));
+
+        let wait_for = |expect_idle: bool| {
+            for _ in 0..100 {
+                let is_idle = idle.idle_ms().unwrap() >= IDLE_TIMEOUT.as_millis() as u64;
+                if is_idle == expect_idle {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            panic!("idle state did not change");
+        };
+
+        wire.send(NOTIFICATION_ID, NOTIFICATION_IDLED_EVENT, &Args::new()).unwrap();
+        wait_for(true);
+        wire.send(NOTIFICATION_ID, NOTIFICATION_RESUMED_EVENT, &Args::new()).unwrap();
+        wait_for(false);
+
+        drop(wire);
+        for _ in 0..100 {
+            if idle.idle_ms().is_none() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("disconnect was not noticed");
+    }
+
+    #[test]
+    fn fails_without_idle_notifier() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let server = std::thread::spawn(move || compositor(server, false));
+
+        let err = WaylandIdle::handshake(client).err().unwrap();
+        assert!(err.contains("ext-idle-notify"), "{}", err);
+        server.join().unwrap();
+    }
+}
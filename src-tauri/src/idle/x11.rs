@@ -0,0 +1,79 @@
+use super::IdleSource;
+use std::ptr;
+use std::sync::Mutex;
+use x11_dl::xlib::{Display, Xlib};
+use x11_dl::xss::{XScreenSaverInfo, Xss};
+
+/// Idle time from the X11 MIT-SCREEN-SAVER extension (libXss, loaded at runtime).
+pub struct X11Idle {
+    connection: Mutex<Connection>,
+}
+
+struct Connection {
+    xlib: Xlib,
+    xss: Xss,
+    display: *mut Display,
+    info: *mut XScreenSaverInfo,
+}
+
+// The display is only ever touched while holding the mutex
+unsafe impl Send for Connection {}
+
+impl X11Idle {
+    pub fn connect() -> Result<Self, String> {
+        let xlib = Xlib::open().map_err(|e| e.to_string())?;
+        let xss = Xss::open().map_err(|e| e.to_string())?;
+
+        unsafe {
+            let display = (xlib.XOpenDisplay)(ptr::null());
+            if display.is_null() {
+                return Err("cannot open X display".to_string());
+            }
+
+            let (mut event_base, mut error_base) = (0, 0);
+            if (xss.XScreenSaverQueryExtension)(display, &mut event_base, &mut error_base) == 0 {
+                (xlib.XCloseDisplay)(display);
+                return Err("X server has no MIT-SCREEN-SAVER extension".to_string());
+            }
+
+            let info = (xss.XScreenSaverAllocInfo)();
+            if info.is_null() {
+                (xlib.XCloseDisplay)(display);
+                return Err("XScreenSaverAllocInfo failed".to_string());
+            }
+
+            log::info!("Idle: using X11 screensaver extension");
+            Ok(Self {
+                connection: Mutex::new(Connection { xlib, xss, display, info }),
+            })
+        }
+    }
+}
+
+impl IdleSource for X11Idle {
+    fn idle_ms(&self) -> Option<u64> {
+        let connection = self.connection.lock().unwrap();
+        unsafe {
+            let root = (connection.xlib.XDefaultRootWindow)(connection.display);
+            if (connection.xss.XScreenSaverQueryInfo)(connection.display, root, connection.info) == 0 {
+                return None;
+            }
+            // c_ulong is only 32 bits on some targets
+            #[allow(clippy::unnecessary_cast)]
+            Some((*connection.info).idle as u64)
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "x11"
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        unsafe {
+            (self.xlib.XFree)(self.info.cast());
+            (self.xlib.XCloseDisplay)(self.display);
+        }
+    }
+}
@@ -0,0 +1,421 @@
+use crate::models::{BreakSettings, LongbreakSchedule};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ImportSource {
+    /// Stretchly's `config.json`.
+    Stretchly,
+    /// Workrave's settings as an INI file or `dconf dump /org/workrave/`.
+    Workrave,
+}
+
+/// Settings taken over from another app, and what couldn't be.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImportReport {
+    pub source: ImportSource,
+    pub settings: BreakSettings,
+    /// Options taken over, each with what it became.
+    pub mapped: Vec<String>,
+    /// Options left out, each with why.
+    pub unmapped: Vec<String>,
+    /// Profile the settings were saved to; `None` when they replaced the current settings.
+    pub profile: Option<String>,
+}
+
+/// What an option becomes: a description and the change to the settings, or why it
+/// is left out.
+type Mapping = Result<(String, Box<dyn Fn(&mut BreakSettings)>), String>;
+
+/// Settings built up from the options of another app. The options are taken over
+/// together if the result is valid; otherwise one by one, leaving out those that would
+/// make the settings invalid.
+struct Mapper {
+    base: BreakSettings,
+    options: Vec<(String, Mapping)>,
+}
+
+impl Mapper {
+    fn apply(&mut self, option: &str, description: String, change: impl Fn(&mut BreakSettings) + 'static) {
+        self.options.push((option.to_string(), Ok((description, Box::new(change)))));
+    }
+
+    fn skip(&mut self, option: &str, reason: &str) {
+        self.options.push((option.to_string(), Err(reason.to_string())));
+    }
+
+    fn report(self, source: ImportSource) -> ImportReport {
+        // One option can make another valid, e.g. a schedule change for an interval
+        let mut combined = self.base.clone();
+        for (_, change) in self.options.iter().filter_map(|(_, mapping)| mapping.as_ref().ok()) {
+            change(&mut combined);
+        }
+        let together = combined.validate().is_ok();
+        let mut settings = if together { combined } else { self.base };
+
+        let (mut mapped, mut unmapped) = (Vec::new(), Vec::new());
+        for (option, mapping) in self.options {
+            match mapping {
+                Ok((description, _)) if together => mapped.push(format!("{}: {}", option, description)),
+                Ok((description, change)) => {
+                    let mut candidate = settings.clone();
+                    change(&mut candidate);
+                    match candidate.validate() {
+                        Ok(()) => {
+                            settings = candidate;
+                            mapped.push(format!("{}: {}", option, description));
+                        }
+                        Err(e) => unmapped.push(format!("{}: {}", option, e)),
+                    }
+                }
+                Err(reason) => unmapped.push(format!("{}: {}", option, reason)),
+            }
+        }
+        ImportReport {
+            source,
+            settings,
+            mapped,
+            unmapped,
+            profile: None,
+        }
+    }
+}
+
+/// `value`, or `u32::MAX` if it doesn't fit, which no setting accepts.
+fn to_u32(value: u64) -> u32 {
+    u32::try_from(value).unwrap_or(u32::MAX)
+}
+
+/// Round to whole units of `unit`, but never down to 0.
+fn whole(value: u64, unit: u64) -> u32 {
+    to_u32(value.saturating_add(unit / 2) / unit).max(1)
+}
+
+/// Map another app's settings onto `base`, telling Stretchly's JSON and Workrave's INI
+/// apart by their contents. Options without a Restie equivalent keep `base`'s values.
+pub fn import(contents: &str, base: BreakSettings) -> Result<ImportReport, String> {
+    let mapper = Mapper { base, options: Vec::new() };
+    if contents.trim_start().starts_with('{') {
+        match serde_json::from_str::<Value>(contents) {
+            Ok(Value::Object(config)) => Ok(from_stretchly(config, mapper)),
+            _ => Err("Not a valid Stretchly config.json".to_string()),
+        }
+    } else if contents.lines().any(|line| line.trim_start().starts_with('[')) {
+        Ok(from_workrave(contents, mapper))
+    } else {
+        Err("Not a Stretchly config.json or Workrave settings export".to_string())
+    }
+}
+
+/// Stretchly options taken over, in the order they are applied.
+const STRETCHLY_OPTIONS: [&str; 12] = [
+    "microbreakInterval",
+    "microbreakDuration",
+    "breakInterval",
+    "breakDuration",
+    "microbreakStrictMode",
+    "breakStrictMode",
+    "fullscreen",
+    "microbreakPostponeTime",
+    "microbreakPostponesLimit",
+    // After the limit, so switching postponing off wins
+    "microbreakPostpone",
+    "microbreakNotification",
+    "microbreakNotificationInterval",
+];
+
+fn from_stretchly(mut config: Map<String, Value>, mut mapper: Mapper) -> ImportReport {
+    let notification = config.get("microbreakNotification").and_then(Value::as_bool);
+    for option in STRETCHLY_OPTIONS {
+        let Some(value) = config.remove(option) else {
+            continue;
+        };
+        // Stretchly keeps times in milliseconds
+        let (number, flag) = (value.as_u64(), value.as_bool());
+        match (option, number, flag) {
+            ("microbreakInterval", Some(ms), _) => {
+                let minutes = whole(ms, 60_000);
+                mapper.apply(option, format!("microbreak every {} min", minutes), move |s| {
+                    s.microbreak_interval_minutes = minutes
+                });
+            }
+            ("microbreakDuration", Some(ms), _) => {
+                let seconds = whole(ms, 1000);
+                mapper.apply(option, format!("microbreaks last {} s", seconds), move |s| {
+                    s.microbreak_duration_seconds = seconds
+                });
+            }
+            // The number of microbreaks between two breaks
+            ("breakInterval", Some(count), _) => {
+                let every = to_u32(count.saturating_add(1));
+                let description = match count {
+                    0 => "every break is a long one".to_string(),
+                    1 => "a long break after every microbreak".to_string(),
+                    count => format!("a long break after every {} microbreaks", count),
+                };
+                mapper.apply(option, description, move |s| {
+                    s.longbreak_schedule = LongbreakSchedule::Microbreaks;
+                    s.longbreak_interval_microbreaks = every;
+                });
+            }
+            ("breakDuration", Some(ms), _) => {
+                let minutes = whole(ms, 60_000);
+                mapper.apply(option, format!("long breaks last {} min", minutes), move |s| {
+                    s.longbreak_duration_minutes = minutes
+                });
+            }
+            ("microbreakStrictMode", _, Some(strict)) => {
+                mapper.apply(option, format!("strict microbreaks {}", on_off(strict)), move |s| {
+                    s.strict_microbreaks = strict
+                });
+            }
+            ("breakStrictMode", _, Some(strict)) => {
+                mapper.apply(option, format!("strict long breaks {}", on_off(strict)), move |s| {
+                    s.strict_longbreaks = strict
+                });
+            }
+            ("fullscreen", _, Some(fullscreen)) => {
+                mapper.apply(option, format!("fullscreen breaks {}", on_off(fullscreen)), move |s| {
+                    s.fullscreen_breaks = fullscreen
+                });
+            }
+            ("microbreakPostponeTime", Some(ms), _) => {
+                let minutes = whole(ms, 60_000);
+                mapper.apply(option, format!("postpone by {} min", minutes), move |s| {
+                    s.postpone_durations_minutes = vec![minutes]
+                });
+            }
+            ("microbreakPostponesLimit", Some(limit), _) => {
+                mapper.apply(option, format!("at most {} postpones per break", limit), move |s| {
+                    s.max_postpones_per_break = to_u32(limit)
+                });
+            }
+            ("microbreakPostpone", _, Some(true)) => {}
+            ("microbreakPostpone", _, Some(false)) => {
+                mapper.apply(option, "postponing off".to_string(), |s| s.max_postpones_per_break = 0);
+            }
+            ("microbreakNotification", _, Some(true)) => {}
+            ("microbreakNotification", _, Some(false)) => {
+                mapper.apply(option, "no warning before breaks".to_string(), |s| s.warning_seconds = 0);
+            }
+            ("microbreakNotificationInterval", Some(_), _) if notification == Some(false) => {}
+            ("microbreakNotificationInterval", Some(ms), _) => {
+                let seconds = to_u32(ms.saturating_add(500) / 1000);
+                mapper.apply(option, format!("warn {} s before breaks", seconds), move |s| s.warning_seconds = seconds);
+            }
+            _ => mapper.skip(option, &format!("unexpected value {}", value)),
+        }
+    }
+
+    for (option, value) in config {
+        let reason = match option.as_str() {
+            "microbreak" if value == Value::Bool(true) => continue,
+            "break" if value == Value::Bool(true) => continue,
+            "naturalBreaks" if value == Value::Bool(true) => continue,
+            "microbreak" => "Restie can't turn microbreaks off",
+            "break" => "Restie can't turn long breaks off",
+            "naturalBreaks" => "Restie always counts time away as a break",
+            "breakPostpone" | "breakPostponeTime" | "breakPostponesLimit" => {
+                "Restie postpones long breaks like microbreaks"
+            }
+            "breakNotification" | "breakNotificationInterval" => "Restie warns before long breaks like microbreaks",
+            _ => "no Restie equivalent",
+        };
+        mapper.skip(&option, reason);
+    }
+    mapper.report(ImportSource::Stretchly)
+}
+
+fn from_workrave(contents: &str, mut mapper: Mapper) -> ImportReport {
+    // Workrave's timers only run while the user is active
+    mapper.apply("timers", "count active time only".to_string(), |s| s.count_active_time_only = true);
+
+    let mut section = String::new();
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            // dconf dumps use dashes and may start from the root
+            section = name.trim_matches('/').replace('-', "_");
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().replace('-', "_");
+        let value = value.trim().trim_matches('\'');
+        let option = format!("{}/{}", section, key);
+        let seconds = value.parse::<u64>().ok();
+
+        match (section.as_str(), key.as_str(), seconds) {
+            ("timers/micro_pause", "limit", Some(seconds)) => {
+                let minutes = whole(seconds, 60);
+                mapper.apply(&option, format!("microbreak every {} min", minutes), move |s| {
+                    s.microbreak_interval_minutes = minutes
+                });
+            }
+            ("timers/micro_pause", "auto_reset", Some(seconds)) => {
+                mapper.apply(&option, format!("microbreaks last {} s", seconds), move |s| {
+                    s.microbreak_duration_seconds = to_u32(seconds)
+                });
+            }
+            ("timers/micro_pause", "snooze", Some(seconds)) => {
+                let minutes = whole(seconds, 60);
+                mapper.apply(&option, format!("postpone by {} min", minutes), move |s| {
+                    s.postpone_durations_minutes = vec![minutes]
+                });
+            }
+            ("timers/rest_break", "limit", Some(seconds)) => {
+                let minutes = whole(seconds, 60);
+                mapper.apply(&option, format!("long break every {} min", minutes), move |s| {
+                    s.longbreak_schedule = LongbreakSchedule::Minutes;
+                    s.longbreak_interval_minutes = minutes;
+                });
+            }
+            ("timers/rest_break", "auto_reset", Some(seconds)) => {
+                let minutes = whole(seconds, 60);
+                mapper.apply(&option, format!("long breaks last {} min", minutes), move |s| {
+                    s.longbreak_duration_minutes = minutes
+                });
+            }
+            ("timers/rest_break", "snooze", _) => mapper.skip(&option, "Restie postpones long breaks like microbreaks"),
+            ("timers/daily_limit", _, _) | ("breaks/daily_limit", _, _) => {
+                mapper.skip(&option, "Restie has no daily limit")
+            }
+            ("breaks/micro_pause" | "breaks/rest_break", "enabled", _) if value == "true" => {}
+            ("breaks/micro_pause", "enabled", _) => mapper.skip(&option, "Restie can't turn microbreaks off"),
+            ("breaks/rest_break", "enabled", _) => mapper.skip(&option, "Restie can't turn long breaks off"),
+            _ => mapper.skip(&option, "no Restie equivalent"),
+        }
+    }
+    mapper.report(ImportSource::Workrave)
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stretchly_config_maps_timings_and_reports_the_rest() {
+        let config = r#"{
+            "microbreak": true,
+            "microbreakInterval": 900000,
+            "microbreakDuration": 30000,
+            "microbreakPostpone": false,
+            "microbreakPostponeTime": 120000,
+            "microbreakPostponesLimit": 2,
+            "microbreakNotification": true,
+            "microbreakNotificationInterval": 10000,
+            "break": true,
+            "breakInterval": 3,
+            "breakDuration": 600000,
+            "breakStrictMode": true,
+            "breakPostponeTime": 300000,
+            "naturalBreaks": true,
+            "language": "en"
+        }"#;
+
+        let report = import(config, BreakSettings::default()).unwrap();
+        assert_eq!(report.source, ImportSource::Stretchly);
+        let settings = &report.settings;
+        assert_eq!(settings.microbreak_interval_minutes, 15);
+        assert_eq!(settings.microbreak_duration_seconds, 30);
+        assert_eq!(settings.longbreak_interval_microbreaks, 4);
+        assert_eq!(settings.longbreak_duration_minutes, 10);
+        assert!(settings.strict_longbreaks);
+        assert_eq!(settings.postpone_durations_minutes, vec![2]);
+        assert_eq!(settings.max_postpones_per_break, 0);
+        assert_eq!(settings.warning_seconds, 10);
+        assert!(report.mapped.contains(&"breakInterval: a long break after every 3 microbreaks".to_string()));
+        assert_eq!(
+            report.unmapped,
+            vec![
+                "breakPostponeTime: Restie postpones long breaks like microbreaks".to_string(),
+                "language: no Restie equivalent".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn workrave_export_maps_timers_and_skips_the_daily_limit() {
+        let export = "\
+[timers/micro-pause]
+limit=180
+auto-reset=15
+snooze=150
+
+[timers/rest-break]
+limit=2700
+auto-reset=600
+
+[timers/daily-limit]
+limit=14400
+
+[breaks/micro-pause]
+enabled=true
+max-preludes=3
+";
+
+        let report = import(export, BreakSettings::default()).unwrap();
+        assert_eq!(report.source, ImportSource::Workrave);
+        let settings = &report.settings;
+        assert!(settings.count_active_time_only);
+        assert_eq!(settings.microbreak_interval_minutes, 3);
+        assert_eq!(settings.microbreak_duration_seconds, 15);
+        assert_eq!(settings.postpone_durations_minutes, vec![3]);
+        assert_eq!(settings.longbreak_schedule, LongbreakSchedule::Minutes);
+        assert_eq!(settings.longbreak_interval_minutes, 45);
+        assert_eq!(settings.longbreak_duration_minutes, 10);
+        assert_eq!(
+            report.unmapped,
+            vec![
+                "timers/daily_limit/limit: Restie has no daily limit".to_string(),
+                "breaks/micro_pause/max_preludes: no Restie equivalent".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn options_that_break_the_settings_are_left_out() {
+        // Shorter than the microbreaks
+        let report = import(r#"{"breakDuration": 10000, "microbreakDuration": 60000}"#, BreakSettings::default()).unwrap();
+        assert_eq!(report.settings.longbreak_duration_minutes, BreakSettings::default().longbreak_duration_minutes);
+        assert_eq!(report.unmapped.len(), 1);
+        assert!(report.unmapped[0].starts_with("breakDuration: "));
+
+        assert!(import("microbreak every 10 minutes", BreakSettings::default()).is_err());
+    }
+
+    #[test]
+    fn options_are_taken_over_together_when_they_fit_together() {
+        // A 40 minute microbreak interval only fits once long breaks follow microbreaks
+        let base = BreakSettings {
+            longbreak_schedule: LongbreakSchedule::Minutes,
+            longbreak_interval_minutes: 30,
+            ..BreakSettings::default()
+        };
+        let report = import(r#"{"microbreakInterval": 2400000, "breakInterval": 3}"#, base).unwrap();
+        assert_eq!(report.settings.microbreak_interval_minutes, 40);
+        assert_eq!(report.settings.longbreak_schedule, LongbreakSchedule::Microbreaks);
+        assert_eq!(report.mapped.len(), 2);
+        assert_eq!(report.unmapped, Vec::<String>::new());
+    }
+
+    #[test]
+    fn numbers_too_big_for_a_setting_are_left_out() {
+        // Would be 1 if cut down to 32 bits
+        let report = import(r#"{"microbreakPostponesLimit": 4294967297}"#, BreakSettings::default()).unwrap();
+        assert_eq!(report.settings.max_postpones_per_break, BreakSettings::default().max_postpones_per_break);
+        assert_eq!(report.unmapped.len(), 1);
+        assert!(report.unmapped[0].starts_with("microbreakPostponesLimit: "));
+    }
+}
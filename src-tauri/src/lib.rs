@@ -1,19 +1,32 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod clock;
+mod config;
+mod events;
+mod export;
+mod fixed_breaks;
+mod history;
+mod idle;
+mod import;
+mod lifecycle;
 mod models;
+mod profiles;
+mod report;
 mod scheduler;
+mod snapshot;
 mod state;
 mod commands;
 mod tray;
+mod working_hours;
+#[cfg(test)]
+mod test_support;
 
 use tauri::Manager;
 
 pub fn run() {
-    let app_state = crate::state::AppState::default();
-
     tauri::Builder::default()
+        .plugin(tauri_plugin_log::Builder::new().build())
         .plugin(tauri_plugin_opener::init())
-        .manage(app_state)
         .invoke_handler(tauri::generate_handler![
             commands::start_break,
             commands::pause_breaks,
@@ -21,13 +34,33 @@ pub fn run() {
             commands::skip_break,
             commands::postpone_break,
             commands::complete_break,
+            commands::start_pomodoro,
+            commands::interrupt_pomodoro,
+            commands::void_pomodoro,
             commands::get_break_state,
+            commands::get_scheduler_state,
+            commands::get_history_summaries,
+            commands::export_history,
+            commands::write_weekly_report,
+            commands::open_latest_report,
             commands::update_settings,
             commands::get_settings,
+            commands::get_settings_notes,
+            commands::get_profiles,
+            commands::create_profile,
+            commands::rename_profile,
+            commands::delete_profile,
+            commands::activate_profile,
+            commands::import_settings,
             commands::set_break_window_mode,
             commands::set_normal_window_mode,
         ])
         .setup(|app| {
+            // Load persisted settings and scheduler progress before anything reads AppState
+            let config_dir = app.path().app_config_dir()?;
+            let data_dir = app.path().app_data_dir()?;
+            app.manage(crate::state::AppState::load(&config_dir, &data_dir));
+
             // Create a hidden anchor window to prevent app from quitting when all visible windows close
             // This is the most reliable cross-platform solution
             use tauri::{WebviewUrl, WebviewWindowBuilder};
@@ -53,6 +86,7 @@ pub fn run() {
             // Set app handle in scheduler FIRST
             let app_state: tauri::State<crate::state::AppState> = app.state();
             let mut scheduler = app_state.scheduler.lock().unwrap();
+            scheduler.set_idle_source(crate::idle::detect());
             scheduler.set_app_handle(app.handle().clone());
             drop(scheduler);
 
@@ -61,6 +95,25 @@ pub fn run() {
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| match event {
+            tauri::RunEvent::Exit => {
+                let app_state: tauri::State<crate::state::AppState> = app.state();
+                app_state.scheduler.lock().unwrap().shutdown();
+            }
+            tauri::RunEvent::WindowEvent {
+                label,
+                event: tauri::WindowEvent::Destroyed,
+                ..
+            } if label == "break" => {
+                // The scheduler may be closing the window itself while holding its lock
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    let app_state: tauri::State<crate::state::AppState> = app.state();
+                    app_state.scheduler.lock().unwrap().break_window_closed();
+                });
+            }
+            _ => {}
+        });
 }
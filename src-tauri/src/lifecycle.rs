@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Where the break cycle currently is. Every command goes through `transition`,
+/// so counters are only touched when the move is legal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BreakPhase {
+    /// Working; the next break is scheduled.
+    #[default]
+    Scheduled,
+    /// The next break is about to start.
+    Warning,
+    /// A break is on screen.
+    Active,
+    /// The user paused breaks.
+    Paused,
+    /// Breaks are switched off by the app (e.g. outside working hours).
+    Disabled,
+}
+
+/// Something that asks the lifecycle to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LifecycleEvent {
+    Warn,
+    Start,
+    Complete,
+    Skip,
+    Postpone,
+    Pause,
+    Resume,
+    Disable,
+    Enable,
+}
+
+/// A command that isn't valid in the current phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransitionError {
+    pub phase: BreakPhase,
+    pub event: LifecycleEvent,
+}
+
+impl fmt::Display for TransitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cannot {:?} a break while {:?}", self.event, self.phase)
+    }
+}
+
+impl std::error::Error for TransitionError {}
+
+/// Why the scheduler refused a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakError {
+    /// The command isn't valid in the current phase.
+    Transition(TransitionError),
+    /// The break hasn't run its course yet.
+    TooEarly { remaining_ms: u64 },
+    /// The delay isn't one of the configured postpone durations.
+    PostponeNotOffered { minutes: u32 },
+    /// This break was already postponed as often as allowed.
+    PostponeLimitReached { max: u32 },
+    /// The last postpone was too recent.
+    PostponeCooldown { remaining_ms: u64 },
+    /// Strict mode: the break can't be skipped or postponed.
+    Strict,
+    /// A timed pause has to end in the future.
+    PauseEndInPast,
+    /// Pomodoro mode is switched off in the settings.
+    PomodoroOff,
+    /// A pomodoro is already running.
+    PomodoroRunning,
+    /// There is no pomodoro to interrupt or void.
+    NoPomodoro,
+    /// Pomodoros can't start while a break is on screen or breaks are off.
+    PomodoroNotNow { phase: BreakPhase },
+}
+
+impl fmt::Display for BreakError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreakError::Transition(e) => e.fmt(f),
+            BreakError::TooEarly { remaining_ms } => {
+                write!(f, "The break isn't over yet ({}s left)", remaining_ms.div_ceil(1000))
+            }
+            BreakError::PostponeNotOffered { minutes } => {
+                write!(f, "Postponing by {} minutes isn't one of the configured choices", minutes)
+            }
+            BreakError::PostponeLimitReached { max } => {
+                write!(f, "This break was already postponed {} times", max)
+            }
+            BreakError::PostponeCooldown { remaining_ms } => {
+                write!(f, "Postponed too recently, try again in {}s", remaining_ms.div_ceil(1000))
+            }
+            BreakError::Strict => write!(f, "Strict mode is on: this break can't be skipped or postponed"),
+            BreakError::PauseEndInPast => write!(f, "A pause has to end in the future"),
+            BreakError::PomodoroOff => write!(f, "Pomodoro mode is switched off"),
+            BreakError::PomodoroRunning => write!(f, "A pomodoro is already running"),
+            BreakError::NoPomodoro => write!(f, "No pomodoro is running"),
+            BreakError::PomodoroNotNow { phase } => write!(f, "Cannot start a pomodoro while {:?}", phase),
+        }
+    }
+}
+
+impl std::error::Error for BreakError {}
+
+impl From<TransitionError> for BreakError {
+    fn from(e: TransitionError) -> Self {
+        BreakError::Transition(e)
+    }
+}
+
+pub fn transition(phase: BreakPhase, event: LifecycleEvent) -> Result<BreakPhase, TransitionError> {
+    use BreakPhase::*;
+    use LifecycleEvent::*;
+
+    match (phase, event) {
+        (Scheduled, Warn) => Ok(Warning),
+        (Scheduled | Warning, Start) => Ok(Active),
+        (Active, Complete) => Ok(Scheduled),
+        // Skipping or postponing works on the upcoming break as well as the current one
+        (Scheduled | Warning | Active, Skip | Postpone) => Ok(Scheduled),
+        (Scheduled | Warning, Pause) => Ok(Paused),
+        (Paused, Resume) => Ok(Scheduled),
+        (Scheduled | Warning | Paused, Disable) => Ok(Disabled),
+        (Disabled, Enable) => Ok(Scheduled),
+        _ => Err(TransitionError { phase, event }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn break_runs_through_warning_to_completion() {
+        let phase = transition(BreakPhase::Scheduled, LifecycleEvent::Warn).unwrap();
+        let phase = transition(phase, LifecycleEvent::Start).unwrap();
+        assert_eq!(phase, BreakPhase::Active);
+        assert_eq!(transition(phase, LifecycleEvent::Complete), Ok(BreakPhase::Scheduled));
+    }
+
+    #[test]
+    fn invalid_moves_are_rejected() {
+        for (phase, event) in [
+            (BreakPhase::Scheduled, LifecycleEvent::Complete),
+            (BreakPhase::Paused, LifecycleEvent::Start),
+            (BreakPhase::Paused, LifecycleEvent::Skip),
+            (BreakPhase::Active, LifecycleEvent::Pause),
+            (BreakPhase::Active, LifecycleEvent::Disable),
+            (BreakPhase::Disabled, LifecycleEvent::Resume),
+        ] {
+            assert_eq!(transition(phase, event), Err(TransitionError { phase, event }));
+        }
+    }
+}
@@ -1,12 +1,190 @@
+use crate::fixed_breaks::FixedBreak;
+use crate::lifecycle::BreakPhase;
+use crate::working_hours::WorkingHours;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Extra enforcement switched on as the user keeps dodging breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EscalationStep {
+    /// Show the break fullscreen.
+    Fullscreen,
+    /// Lengthen microbreaks by `escalation_extra_seconds`.
+    LongerMicrobreaks,
+    /// No skipping or postponing.
+    Strict,
+}
+
+/// What decides when the next long break is due.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LongbreakSchedule {
+    /// Every `longbreak_interval_microbreaks`-th break is a long one.
+    #[default]
+    Microbreaks,
+    /// A long break every `longbreak_interval_minutes`, with microbreaks in between.
+    Minutes,
+}
+
+/// How a break is put on screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Presentation {
+    #[default]
+    Window,
+    Fullscreen,
+}
+
+/// A user-defined break (eye rest, hydrate, ...) running on its own timer, next to
+/// the microbreak/long break cycle.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BreakKind {
+    pub name: String,
+    pub interval_minutes: u32,
+    pub duration_seconds: u32,
+    /// Can't be skipped or postponed.
+    pub strict: bool,
+    pub presentation: Presentation,
+    /// Shown in the break window, e.g. what to do.
+    pub message: String,
+}
+
+impl BreakKind {
+    pub fn interval_ms(&self) -> u64 {
+        self.interval_minutes as u64 * 60 * 1000
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Break kinds need a name".to_string());
+        }
+        if self.interval_minutes < 1 || self.interval_minutes > 480 {
+            return Err(format!("Invalid interval for {}: must be 1-480 minutes", self.name));
+        }
+        if self.duration_seconds < 5 || self.duration_seconds > 3600 {
+            return Err(format!("Invalid duration for {}: must be 5-3600 seconds", self.name));
+        }
+        if self.message.chars().count() > 200 {
+            return Err(format!("Message for {} is too long: at most 200 characters", self.name));
+        }
+        Ok(())
+    }
+}
+
+/// How Pomodoro sessions relate to the microbreak/long break cycle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PomodoroMode {
+    #[default]
+    Off,
+    /// Pomodoros run next to the interval breaks.
+    Alongside,
+    /// Pomodoros replace the interval breaks; fixed breaks and break kinds still run.
+    Instead,
+}
+
+/// Work sessions started by hand, each followed by a short break, with a long break
+/// after every `long_break_every` sessions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PomodoroSettings {
+    pub mode: PomodoroMode,
+    pub work_minutes: u32,
+    pub short_break_minutes: u32,
+    pub long_break_minutes: u32,
+    pub long_break_every: u32,
+}
+
+impl Default for PomodoroSettings {
+    fn default() -> Self {
+        Self {
+            mode: PomodoroMode::Off,
+            work_minutes: 25,
+            short_break_minutes: 5,
+            long_break_minutes: 15,
+            long_break_every: 4,
+        }
+    }
+}
+
+impl PomodoroSettings {
+    fn validate(&self) -> Result<(), String> {
+        if self.work_minutes < 5 || self.work_minutes > 120 {
+            return Err("Invalid pomodoro length: must be 5-120 minutes".to_string());
+        }
+        if self.short_break_minutes < 1 || self.short_break_minutes > 30 {
+            return Err("Invalid pomodoro break: must be 1-30 minutes".to_string());
+        }
+        if self.long_break_minutes < 1 || self.long_break_minutes > 60 {
+            return Err("Invalid long pomodoro break: must be 1-60 minutes".to_string());
+        }
+        if self.long_break_every < 1 || self.long_break_every > 10 {
+            return Err("Invalid long pomodoro break interval: must be every 1-10 pomodoros".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReportFormat {
+    #[default]
+    Markdown,
+    /// A single HTML file with its styles inline.
+    Html,
+}
+
+/// Weekly compliance reports, written each Monday for the week before.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportSettings {
+    pub enabled: bool,
+    pub format: ReportFormat,
+    /// Where reports go; empty for a "Restie Reports" folder in the user's documents.
+    pub folder: String,
+    /// Pop up a note when a report is ready.
+    pub notify: bool,
+}
+
+impl ReportSettings {
+    fn validate(&self) -> Result<(), String> {
+        if !self.folder.is_empty() && !std::path::Path::new(&self.folder).is_absolute() {
+            return Err("Invalid report folder: must be an absolute path".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BreakSettings {
     pub microbreak_interval_minutes: u32,
     pub microbreak_duration_seconds: u32,
+    pub longbreak_schedule: LongbreakSchedule,
     pub longbreak_interval_microbreaks: u32,
+    pub longbreak_interval_minutes: u32,
     pub longbreak_duration_minutes: u32,
     pub fullscreen_breaks: bool,
+    /// Heads-up before each break, during which it can be started early or postponed
+    /// (0 starts breaks without warning).
+    pub warning_seconds: u32,
+    /// Delays offered for postponing a break, shortest first.
+    pub postpone_durations_minutes: Vec<u32>,
+    /// How often one break can be postponed (0 disables postponing).
+    pub max_postpones_per_break: u32,
+    /// Minimum time between two postpones of the same break.
+    pub postpone_cooldown_seconds: u32,
+    /// Strict breaks can't be skipped or postponed.
+    pub strict_microbreaks: bool,
+    pub strict_longbreaks: bool,
+    /// Consecutive skips/postpones per escalation level (0 disables escalation).
+    pub escalation_after_dodges: u32,
+    /// Steps in the order they kick in: level `n` applies the first `n`.
+    pub escalation_steps: Vec<EscalationStep>,
+    pub escalation_extra_seconds: u32,
+    /// Only count time the user is active towards the next break (needs idle detection).
+    pub count_active_time_only: bool,
+    /// When breaks run; outside these hours the scheduler is dormant.
+    pub working_hours: WorkingHours,
+    /// Breaks at set times of day (lunch, ...), on top of the interval breaks.
+    pub fixed_breaks: Vec<FixedBreak>,
+    /// User-defined breaks, each on its own timer.
+    pub break_kinds: Vec<BreakKind>,
+    pub pomodoro: PomodoroSettings,
+    pub reports: ReportSettings,
 }
 
 impl Default for BreakSettings {
@@ -14,57 +192,363 @@ impl Default for BreakSettings {
         Self {
             microbreak_interval_minutes: 20,
             microbreak_duration_seconds: 20,
+            longbreak_schedule: LongbreakSchedule::Microbreaks,
             longbreak_interval_microbreaks: 4,
+            longbreak_interval_minutes: 90,
             longbreak_duration_minutes: 5,
             fullscreen_breaks: false,
+            warning_seconds: 30,
+            postpone_durations_minutes: vec![5, 10],
+            max_postpones_per_break: 3,
+            postpone_cooldown_seconds: 60,
+            strict_microbreaks: false,
+            strict_longbreaks: false,
+            escalation_after_dodges: 0,
+            escalation_steps: vec![
+                EscalationStep::Fullscreen,
+                EscalationStep::LongerMicrobreaks,
+                EscalationStep::Strict,
+            ],
+            escalation_extra_seconds: 20,
+            count_active_time_only: false,
+            working_hours: WorkingHours::default(),
+            fixed_breaks: Vec::new(),
+            break_kinds: Vec::new(),
+            pomodoro: PomodoroSettings::default(),
+            reports: ReportSettings::default(),
+        }
+    }
+}
+
+impl BreakSettings {
+    /// Range and consistency checks shared by `update_settings` and the config file loader.
+    pub fn validate(&self) -> Result<(), String> {
+        self.validate_ranges()?;
+        self.validate_consistency()
+    }
+
+    /// Checks each field on its own, so they can be judged one at a time.
+    pub fn validate_ranges(&self) -> Result<(), String> {
+        if self.microbreak_interval_minutes < 1 || self.microbreak_interval_minutes > 60 {
+            return Err("Invalid microbreak interval: must be 1-60 minutes".to_string());
+        }
+        if self.microbreak_duration_seconds < 5 || self.microbreak_duration_seconds > 300 {
+            return Err("Invalid microbreak duration: must be 5-300 seconds".to_string());
+        }
+        if self.longbreak_interval_microbreaks < 1 || self.longbreak_interval_microbreaks > 10 {
+            return Err("Invalid long break interval: must be 1-10 microbreaks".to_string());
+        }
+        if self.longbreak_interval_minutes < 10 || self.longbreak_interval_minutes > 240 {
+            return Err("Invalid long break interval: must be 10-240 minutes".to_string());
+        }
+        if self.longbreak_duration_minutes < 1 || self.longbreak_duration_minutes > 60 {
+            return Err("Invalid long break duration: must be 1-60 minutes".to_string());
+        }
+        if self.warning_seconds > 300 {
+            return Err("Invalid break warning: must be 0-300 seconds".to_string());
+        }
+        if self.postpone_durations_minutes.is_empty() || self.postpone_durations_minutes.len() > 5 {
+            return Err("Invalid postpone durations: must offer 1-5 choices".to_string());
+        }
+        if self.postpone_durations_minutes.iter().any(|&minutes| !(1..=60).contains(&minutes)) {
+            return Err("Invalid postpone duration: must be 1-60 minutes".to_string());
+        }
+        if !self.postpone_durations_minutes.windows(2).all(|pair| pair[0] < pair[1]) {
+            return Err("Postpone durations must be listed shortest first, without repeats".to_string());
         }
+        if self.max_postpones_per_break > 10 {
+            return Err("Invalid postpone limit: must be 0-10 per break".to_string());
+        }
+        if self.postpone_cooldown_seconds > 3600 {
+            return Err("Invalid postpone cooldown: must be 0-3600 seconds".to_string());
+        }
+        if self.escalation_after_dodges > 10 {
+            return Err("Invalid escalation threshold: must be 0-10 skips or postpones".to_string());
+        }
+        if self.escalation_steps.iter().enumerate().any(|(i, step)| self.escalation_steps[..i].contains(step)) {
+            return Err("Escalation steps must not repeat".to_string());
+        }
+        if self.escalation_extra_seconds > 300 {
+            return Err("Invalid escalation extra time: must be 0-300 seconds".to_string());
+        }
+        if self.fixed_breaks.len() > 10 {
+            return Err("Invalid fixed breaks: at most 10".to_string());
+        }
+        for (i, fixed) in self.fixed_breaks.iter().enumerate() {
+            fixed.validate()?;
+            if self.fixed_breaks[..i].iter().any(|other| other.name == fixed.name) {
+                return Err(format!("There is more than one fixed break called {}", fixed.name));
+            }
+        }
+        if self.break_kinds.len() > 10 {
+            return Err("Invalid break kinds: at most 10".to_string());
+        }
+        for (i, kind) in self.break_kinds.iter().enumerate() {
+            kind.validate()?;
+            if self.break_kinds[..i].iter().any(|other| other.name == kind.name) {
+                return Err(format!("There is more than one break kind called {}", kind.name));
+            }
+        }
+        self.pomodoro.validate()?;
+        self.reports.validate()?;
+        self.working_hours.validate()
+    }
+
+    /// Checks between fields.
+    pub fn validate_consistency(&self) -> Result<(), String> {
+        match self.conflict() {
+            Some((message, _)) => Err(message.to_string()),
+            None => Ok(()),
+        }
+    }
+
+    /// The first check between fields that fails, with the fields to reset to their
+    /// defaults, one at a time, until it passes.
+    pub fn conflict(&self) -> Option<(&'static str, &'static [&'static str])> {
+        // Otherwise every break would be a long one
+        if self.longbreak_schedule == LongbreakSchedule::Minutes
+            && self.longbreak_interval_minutes <= self.microbreak_interval_minutes
+        {
+            return Some((
+                "Long break interval must be longer than the microbreak interval",
+                &["longbreak_interval_minutes"],
+            ));
+        }
+        if self.longbreak_duration_minutes * 60 <= self.microbreak_duration_seconds {
+            return Some((
+                "Long breaks must be longer than microbreaks",
+                &["longbreak_duration_minutes", "microbreak_duration_seconds"],
+            ));
+        }
+        None
+    }
+
+    /// Escalation level reached after `dodges` consecutive skips/postpones.
+    pub fn escalation_level(&self, dodges: u32) -> u32 {
+        if self.escalation_after_dodges == 0 {
+            return 0;
+        }
+        (dodges / self.escalation_after_dodges).min(self.escalation_steps.len() as u32)
+    }
+
+    pub fn escalation_for(&self, level: u32) -> &[EscalationStep] {
+        &self.escalation_steps[..(level as usize).min(self.escalation_steps.len())]
+    }
+
+    pub fn is_strict(&self, break_type: &BreakType) -> bool {
+        match break_type {
+            BreakType::Microbreak => self.strict_microbreaks,
+            BreakType::Longbreak => self.strict_longbreaks,
+            BreakType::Fixed(name) => self.fixed_break(name).is_some_and(|fixed| fixed.strict),
+            BreakType::Custom(name) => self.break_kind(name).is_some_and(|kind| kind.strict),
+            // Pomodoro breaks are taken on the user's own initiative
+            BreakType::PomodoroBreak | BreakType::PomodoroLongBreak => false,
+        }
+    }
+
+    pub fn fixed_break(&self, name: &str) -> Option<&FixedBreak> {
+        self.fixed_breaks.iter().find(|fixed| fixed.name == name)
+    }
+
+    pub fn break_kind(&self, name: &str) -> Option<&BreakKind> {
+        self.break_kinds.iter().find(|kind| kind.name == name)
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BreakType {
     Microbreak,
     Longbreak,
+    /// One of `BreakSettings::fixed_breaks`, by name.
+    Fixed(String),
+    /// One of `BreakSettings::break_kinds`, by name.
+    Custom(String),
+    /// The break after a pomodoro.
+    PomodoroBreak,
+    /// The break after every `PomodoroSettings::long_break_every`-th pomodoro.
+    PomodoroLongBreak,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl BreakType {
+    /// How long a break of this type lasts.
+    pub fn duration_ms(&self, settings: &BreakSettings) -> u64 {
+        match self {
+            BreakType::Microbreak => settings.microbreak_duration_seconds as u64 * 1000,
+            BreakType::Longbreak => settings.longbreak_duration_minutes as u64 * 60 * 1000,
+            // A fixed break removed from the settings meanwhile lasts as long as a long break
+            BreakType::Fixed(name) => match settings.fixed_break(name) {
+                Some(fixed) => fixed.duration_minutes as u64 * 60 * 1000,
+                None => settings.longbreak_duration_minutes as u64 * 60 * 1000,
+            },
+            BreakType::Custom(name) => match settings.break_kind(name) {
+                Some(kind) => kind.duration_seconds as u64 * 1000,
+                None => settings.microbreak_duration_seconds as u64 * 1000,
+            },
+            BreakType::PomodoroBreak => settings.pomodoro.short_break_minutes as u64 * 60 * 1000,
+            BreakType::PomodoroLongBreak => settings.pomodoro.long_break_minutes as u64 * 60 * 1000,
+        }
+    }
+
+    pub fn is_pomodoro(&self) -> bool {
+        matches!(self, BreakType::PomodoroBreak | BreakType::PomodoroLongBreak)
+    }
+}
+
+/// A running pomodoro work session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pomodoro {
+    /// Unix ms.
+    pub started_at: u64,
+    pub ends_at: u64,
+    /// What the session is for, if the user said.
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct BreakState {
     pub break_type: Option<BreakType>,
     pub break_number: u32,
     pub microbreaks_since_longbreak: u32,
-    pub is_paused: bool,
-    pub is_break_active: bool,
+    #[serde(default)]
+    pub phase: BreakPhase,
+    /// Postpones over the app's lifetime.
     pub postpone_count: u32,
+    /// Postpones of the upcoming (or current) break.
+    #[serde(default)]
+    pub postpones_this_break: u32,
+    #[serde(default)]
+    pub last_postponed_at: Option<u64>,
     pub skip_count: u32,
+    /// Breaks the user took on their own by being idle long enough.
+    #[serde(default)]
+    pub natural_break_count: u32,
+    /// When the current long-break cycle started (Unix ms), for `LongbreakSchedule::Minutes`.
+    #[serde(default)]
+    pub longbreak_cycle_started_at: Option<u64>,
+    /// When the break on screen started and when it ends (Unix ms).
+    #[serde(default)]
+    pub break_started_at: Option<u64>,
+    #[serde(default)]
+    pub break_ends_at: Option<u64>,
+    /// The break on screen, or the one being warned about, can't be skipped or postponed;
+    /// the break window and warning prompt hide those controls.
+    #[serde(default)]
+    pub strict: bool,
+    /// When a timed pause ends (Unix ms); `None` while paused means until resumed.
+    #[serde(default)]
+    pub paused_until: Option<u64>,
+    /// Skips and postpones since the last completed break.
+    #[serde(default)]
+    pub consecutive_dodges: u32,
+    #[serde(default)]
+    pub escalation_level: u32,
+    /// Escalation steps applied to the break on screen, so the window can say why.
+    #[serde(default)]
+    pub escalation: Vec<EscalationStep>,
+    /// When the last fixed break that was shown or skipped was due, so it isn't
+    /// scheduled again.
+    #[serde(default)]
+    pub last_fixed_break_at: Option<u64>,
+    /// When the break being dealt with first came due (Unix ms), to tell how late it is
+    /// taken after postponing.
+    #[serde(default)]
+    pub break_due_at: Option<u64>,
+    /// When the warning ends and the break starts (Unix ms), during `BreakPhase::Warning`.
+    #[serde(default)]
+    pub warning_ends_at: Option<u64>,
+    /// When each break kind is next due, by name.
+    #[serde(default)]
+    pub kind_due_at: BTreeMap<String, u64>,
+    /// The pomodoro in progress. It stays set while its break is on screen.
+    #[serde(default)]
+    pub pomodoro: Option<Pomodoro>,
+    #[serde(default)]
+    pub pomodoros_since_long_break: u32,
+    /// Weekly reports are written for every week before this one (Unix ms, a Monday
+    /// midnight).
+    #[serde(default)]
+    pub reported_until: Option<u64>,
 }
 
-impl Default for BreakState {
-    fn default() -> Self {
-        Self {
-            break_type: None,
-            break_number: 0,
-            microbreaks_since_longbreak: 0,
-            is_paused: false,
-            is_break_active: false,
-            postpone_count: 0,
-            skip_count: 0,
-        }
-    }
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SchedulerState {
     pub scheduled_break_time: Option<u64>, // Unix timestamp in milliseconds
     pub current_break_type: Option<BreakType>,
     pub time_left_ms: Option<u64>,
+    /// `time_left_ms` is active time rather than wall-clock time.
+    #[serde(default)]
+    pub active_time_only: bool,
+    /// How many more times the upcoming break can be postponed.
+    #[serde(default)]
+    pub postpones_left: u32,
+    /// Earliest time the next postpone is accepted (Unix ms), while in cooldown.
+    #[serde(default)]
+    pub postpone_available_at: Option<u64>,
 }
 
-impl Default for SchedulerState {
-    fn default() -> Self {
-        Self {
-            scheduled_break_time: None,
-            current_break_type: None,
-            time_left_ms: None,
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_are_valid() {
+        assert_eq!(BreakSettings::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn long_break_interval_must_leave_room_for_microbreaks() {
+        let settings = BreakSettings {
+            longbreak_schedule: LongbreakSchedule::Minutes,
+            microbreak_interval_minutes: 30,
+            longbreak_interval_minutes: 30,
+            ..BreakSettings::default()
+        };
+        assert!(settings.validate().is_err());
+
+        // Only checked when the interval is actually used
+        let settings = BreakSettings {
+            longbreak_schedule: LongbreakSchedule::Microbreaks,
+            ..settings
+        };
+        assert_eq!(settings.validate(), Ok(()));
+    }
+
+    #[test]
+    fn postpone_durations_must_be_sorted_and_in_range() {
+        let with_durations = |durations: Vec<u32>| BreakSettings {
+            postpone_durations_minutes: durations,
+            ..BreakSettings::default()
+        };
+        assert_eq!(with_durations(vec![1, 15, 60]).validate(), Ok(()));
+        assert!(with_durations(vec![]).validate().is_err());
+        assert!(with_durations(vec![10, 5]).validate().is_err());
+        assert!(with_durations(vec![5, 5]).validate().is_err());
+        assert!(with_durations(vec![0]).validate().is_err());
+    }
+
+    #[test]
+    fn escalation_level_is_capped_by_the_configured_steps() {
+        let settings = BreakSettings {
+            escalation_after_dodges: 2,
+            escalation_steps: vec![EscalationStep::Fullscreen, EscalationStep::Strict],
+            ..BreakSettings::default()
+        };
+        assert_eq!(settings.escalation_level(1), 0);
+        assert_eq!(settings.escalation_level(2), 1);
+        assert_eq!(settings.escalation_level(9), 2);
+        assert_eq!(settings.escalation_for(1), &[EscalationStep::Fullscreen]);
+
+        let off = BreakSettings::default();
+        assert_eq!(off.escalation_level(9), 0);
+    }
+
+    #[test]
+    fn long_break_must_outlast_a_microbreak() {
+        let settings = BreakSettings {
+            microbreak_duration_seconds: 120,
+            longbreak_duration_minutes: 2,
+            ..BreakSettings::default()
+        };
+        assert!(settings.validate().is_err());
     }
 }
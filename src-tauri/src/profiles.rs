@@ -0,0 +1,261 @@
+use crate::models::BreakSettings;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const PROFILES_FILE: &str = "profiles.json";
+
+const MAX_PROFILES: usize = 20;
+const MAX_PROFILE_NAME_CHARS: usize = 40;
+
+/// A named set of settings the user can switch to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    pub settings: BreakSettings,
+}
+
+/// Every profile, and the one in use. Edits to the settings go to the active profile;
+/// with none active the settings aren't saved to any profile.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profiles {
+    pub active: Option<String>,
+    pub profiles: Vec<Profile>,
+}
+
+/// What the frontend and tray show of the profiles.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProfileList {
+    pub active: Option<String>,
+    pub names: Vec<String>,
+}
+
+impl Profiles {
+    pub fn list(&self) -> ProfileList {
+        ProfileList {
+            active: self.active.clone(),
+            names: self.profiles.iter().map(|profile| profile.name.clone()).collect(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// Trimmed `name` if it is usable for a new profile.
+    fn check_new_name(&self, name: &str) -> Result<String, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Profiles need a name".to_string());
+        }
+        if name.chars().count() > MAX_PROFILE_NAME_CHARS {
+            return Err(format!("Profile name is too long: at most {} characters", MAX_PROFILE_NAME_CHARS));
+        }
+        if self.get(name).is_some() {
+            return Err(format!("There already is a profile called {}", name));
+        }
+        Ok(name.to_string())
+    }
+
+    /// Add a profile with `settings` and make it the active one.
+    pub fn create(&mut self, name: &str, settings: BreakSettings) -> Result<(), String> {
+        self.active = Some(self.add(name, settings)?);
+        Ok(())
+    }
+
+    /// Add a profile with `settings`, leaving the active one as it is. Returns the
+    /// name it was saved under.
+    pub fn add(&mut self, name: &str, settings: BreakSettings) -> Result<String, String> {
+        let name = self.check_new_name(name)?;
+        if self.profiles.len() >= MAX_PROFILES {
+            return Err(format!("At most {} profiles", MAX_PROFILES));
+        }
+        self.profiles.push(Profile { name: name.clone(), settings });
+        Ok(name)
+    }
+
+    pub fn rename(&mut self, from: &str, to: &str) -> Result<(), String> {
+        let to = self.check_new_name(to)?;
+        let profile = self
+            .profiles
+            .iter_mut()
+            .find(|profile| profile.name == from)
+            .ok_or_else(|| format!("No profile called {}", from))?;
+        profile.name = to.clone();
+        if self.active.as_deref() == Some(from) {
+            self.active = Some(to);
+        }
+        Ok(())
+    }
+
+    /// Remove a profile. Deleting the active one keeps its settings in use, without a
+    /// profile.
+    pub fn delete(&mut self, name: &str) -> Result<(), String> {
+        let count = self.profiles.len();
+        self.profiles.retain(|profile| profile.name != name);
+        if self.profiles.len() == count {
+            return Err(format!("No profile called {}", name));
+        }
+        if self.active.as_deref() == Some(name) {
+            self.active = None;
+        }
+        Ok(())
+    }
+
+    /// Make `name` the active profile and return its settings.
+    pub fn activate(&mut self, name: &str) -> Result<BreakSettings, String> {
+        let settings = self.get(name).ok_or_else(|| format!("No profile called {}", name))?.settings.clone();
+        self.active = Some(name.to_string());
+        Ok(settings)
+    }
+
+    /// Store `settings` in the active profile. Returns whether anything changed.
+    pub fn update_active(&mut self, settings: &BreakSettings) -> bool {
+        let Some(active) = self.active.as_deref() else {
+            return false;
+        };
+        match self.profiles.iter_mut().find(|profile| profile.name == active) {
+            Some(profile) if profile.settings != *settings => {
+                profile.settings = settings.clone();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+pub fn profiles_path(config_dir: &Path) -> PathBuf {
+    config_dir.join(PROFILES_FILE)
+}
+
+/// Load the profiles in `path`, never failing. Each profile's settings are migrated
+/// and repaired like the settings file; profiles that can't be read are dropped.
+pub fn load(path: &Path) -> Profiles {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Profiles::default(),
+        Err(e) => {
+            println!("Warning: could not read profiles {:?}: {}", path, e);
+            return Profiles::default();
+        }
+    };
+    let mut fields = match serde_json::from_str::<Value>(&contents) {
+        Ok(Value::Object(fields)) => fields,
+        Ok(_) | Err(_) => {
+            println!("Warning: profiles file {:?} is corrupt, ignoring it", path);
+            return Profiles::default();
+        }
+    };
+
+    let mut profiles = Profiles::default();
+    let entries = match fields.remove("profiles") {
+        Some(Value::Array(entries)) => entries,
+        _ => Vec::new(),
+    };
+    for entry in entries {
+        let Value::Object(mut entry) = entry else {
+            continue;
+        };
+        let Some(Value::String(name)) = entry.remove("name") else {
+            println!("Profiles: skipping a profile without a name");
+            continue;
+        };
+        let mut notes = Vec::new();
+        let settings = crate::config::settings_from_json(entry.remove("settings").unwrap_or_default(), &mut notes);
+        for note in notes {
+            println!("Profile {}: {}", name, note);
+        }
+        if let Err(e) = profiles.add(&name, settings) {
+            println!("Profiles: skipping {}: {}", name, e);
+        }
+    }
+    profiles.active = match fields.remove("active") {
+        Some(Value::String(active)) if profiles.get(&active).is_some() => Some(active),
+        _ => None,
+    };
+    profiles
+}
+
+pub fn save(path: &Path, profiles: &Profiles) -> Result<(), String> {
+    let entries = profiles
+        .profiles
+        .iter()
+        .map(|profile| {
+            let mut entry = Map::new();
+            entry.insert("name".to_string(), Value::from(profile.name.clone()));
+            entry.insert("settings".to_string(), crate::config::settings_to_json(&profile.settings)?);
+            Ok(Value::Object(entry))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let mut fields = Map::new();
+    fields.insert("active".to_string(), profiles.active.clone().map_or(Value::Null, Value::from));
+    fields.insert("profiles".to_string(), Value::Array(entries));
+
+    let json = serde_json::to_string_pretty(&fields).map_err(|e| format!("Failed to serialize profiles: {}", e))?;
+    crate::config::write_atomic(path, json.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn coding() -> BreakSettings {
+        BreakSettings {
+            microbreak_interval_minutes: 30,
+            ..BreakSettings::default()
+        }
+    }
+
+    #[test]
+    fn profiles_are_created_renamed_and_deleted_by_name() {
+        let mut profiles = Profiles::default();
+        profiles.create(" Coding ", coding()).unwrap();
+        profiles.create("Meetings", BreakSettings::default()).unwrap();
+        assert_eq!(profiles.active.as_deref(), Some("Meetings"));
+        assert!(profiles.create("Coding", BreakSettings::default()).is_err());
+        assert!(profiles.create("  ", BreakSettings::default()).is_err());
+
+        assert_eq!(profiles.activate("Coding").unwrap(), coding());
+        profiles.rename("Coding", "Deep work").unwrap();
+        assert_eq!(profiles.active.as_deref(), Some("Deep work"));
+        assert!(profiles.rename("Deep work", "Meetings").is_err());
+
+        assert!(profiles.update_active(&BreakSettings::default()));
+        assert!(!profiles.update_active(&BreakSettings::default()));
+        assert_eq!(profiles.get("Deep work").unwrap().settings, BreakSettings::default());
+
+        profiles.delete("Deep work").unwrap();
+        assert_eq!(profiles.active, None);
+        assert!(profiles.delete("Deep work").is_err());
+        assert!(!profiles.update_active(&coding()));
+        assert_eq!(profiles.list().names, vec!["Meetings".to_string()]);
+    }
+
+    #[test]
+    fn profiles_survive_a_round_trip_and_old_settings_are_migrated() {
+        let dir = TempDir::new("profiles");
+        let path = profiles_path(&dir);
+        let mut profiles = Profiles::default();
+        profiles.create("Coding", coding()).unwrap();
+        profiles.create("Gaming", BreakSettings::default()).unwrap();
+        profiles.activate("Coding").unwrap();
+        save(&path, &profiles).unwrap();
+        assert_eq!(load(&path), profiles);
+
+        // Settings from before `count_active_time_only` existed
+        fs::write(
+            &path,
+            r#"{"active": "Old", "profiles": [{"name": "Old", "settings": {"schema_version": 1,
+                "microbreak_interval_minutes": 25, "microbreak_duration_seconds": 20,
+                "longbreak_interval_microbreaks": 4, "longbreak_duration_minutes": 5,
+                "fullscreen_breaks": false}}, {"settings": {}}]}"#,
+        )
+        .unwrap();
+        let loaded = load(&path);
+        assert_eq!(loaded.list().names, vec!["Old".to_string()]);
+        assert_eq!(loaded.active.as_deref(), Some("Old"));
+        assert_eq!(loaded.get("Old").unwrap().settings.microbreak_interval_minutes, 25);
+    }
+}
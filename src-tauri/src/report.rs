@@ -0,0 +1,263 @@
+use crate::history::{self, HistoryRecord, HistorySummary};
+use crate::models::{ReportFormat, ReportSettings};
+use chrono::{Datelike, NaiveDate, TimeZone, Timelike};
+use std::fmt::Write;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+/// Folder in the user's documents that reports go to unless one is configured.
+const REPORTS_FOLDER: &str = "Restie Reports";
+
+/// Report files are `restie-week-<Monday>.<md|html>`.
+const FILE_PREFIX: &str = "restie-week-";
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// How breaks went in one week.
+pub struct WeeklyReport {
+    /// The Monday the week starts on.
+    pub week: NaiveDate,
+    pub summary: HistorySummary,
+    /// Breaks taken by weekday (Monday first) and the hour they started in.
+    pub heatmap: [[u32; 24]; 7],
+}
+
+impl WeeklyReport {
+    pub fn new<Tz: TimeZone>(tz: &Tz, records: &[HistoryRecord], week: NaiveDate) -> Option<Self> {
+        let summary = history::days_summary(tz, records, week, 7)?;
+        let mut heatmap = [[0; 24]; 7];
+        let taken = records
+            .iter()
+            .filter(|record| (summary.from..summary.to).contains(&record.at))
+            .filter_map(HistoryRecord::rest);
+        for (start, _) in taken {
+            if let Some(time) = tz.timestamp_millis_opt(start as i64).single() {
+                heatmap[time.weekday().num_days_from_monday() as usize][time.hour() as usize] += 1;
+            }
+        }
+        Some(Self { week, summary, heatmap })
+    }
+
+    fn title(&self) -> String {
+        format!("Break report for the week of {}", self.week.format("%-d %B %Y"))
+    }
+
+    /// The headline numbers, as label and value.
+    fn figures(&self) -> Vec<(&'static str, String)> {
+        let summary = &self.summary;
+        let due = summary.breaks_taken + summary.breaks_skipped;
+        let share = |count: u32| match due {
+            0 => count.to_string(),
+            due => format!("{} ({:.0}%)", count, count as f64 * 100.0 / due as f64),
+        };
+        vec![
+            ("Breaks due", due.to_string()),
+            ("Breaks taken", share(summary.breaks_taken)),
+            ("Breaks skipped", share(summary.breaks_skipped)),
+            ("Postpones (share of breaks due)", share(summary.postpones)),
+            ("Average delay", summary.average_delay_ms.map_or_else(|| "-".to_string(), format_duration)),
+            ("Longest stretch without a break", format_duration(summary.longest_stretch_ms)),
+            ("Pomodoros completed", summary.pomodoros_completed.to_string()),
+        ]
+    }
+
+    /// Hours of the day the heatmap shows: those from the first to the last with a break.
+    fn hours(&self) -> Option<RangeInclusive<usize>> {
+        let used = |hour: &usize| self.heatmap.iter().any(|day| day[*hour] > 0);
+        let first = (0..24).find(used)?;
+        let last = (0..24).rev().find(used)?;
+        Some(first..=last)
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n\n| | |\n|---|---:|\n", self.title());
+        for (label, value) in self.figures() {
+            let _ = writeln!(out, "| {} | {} |", label, value);
+        }
+        out.push_str("\n## Breaks taken by hour\n\n");
+        let Some(hours) = self.hours() else {
+            out.push_str("No breaks taken this week.\n");
+            return out;
+        };
+        out.push_str("| |");
+        for hour in hours.clone() {
+            let _ = write!(out, " {:02} |", hour);
+        }
+        out.push_str("\n|---|");
+        out.push_str(&"---:|".repeat(hours.clone().count()));
+        out.push('\n');
+        for (weekday, day) in WEEKDAYS.iter().zip(&self.heatmap) {
+            let _ = write!(out, "| {} |", weekday);
+            for &count in &day[hours.clone()] {
+                match count {
+                    0 => out.push_str(" · |"),
+                    count => {
+                        let _ = write!(out, " {} |", count);
+                    }
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// A self-contained page, styles inline.
+    pub fn to_html(&self) -> String {
+        let title = self.title();
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>\n\
+             body {{ font-family: system-ui, sans-serif; margin: 2rem; color: #222; }}\n\
+             table {{ border-collapse: collapse; margin-bottom: 2rem; }}\n\
+             th, td {{ padding: 4px 10px; border: 1px solid #ddd; }}\n\
+             .figures td:last-child {{ text-align: right; }}\n\
+             .heatmap td {{ text-align: center; min-width: 2em; }}\n\
+             </style>\n</head>\n<body>\n<h1>{title}</h1>\n<table class=\"figures\">\n"
+        );
+        for (label, value) in self.figures() {
+            let _ = writeln!(out, "<tr><td>{}</td><td>{}</td></tr>", label, value);
+        }
+        out.push_str("</table>\n<h2>Breaks taken by hour</h2>\n");
+        match self.hours() {
+            None => out.push_str("<p>No breaks taken this week.</p>\n"),
+            Some(hours) => {
+                let busiest = self.heatmap.iter().flatten().copied().max().unwrap_or(1).max(1);
+                out.push_str("<table class=\"heatmap\">\n<tr><th></th>");
+                for hour in hours.clone() {
+                    let _ = write!(out, "<th>{:02}</th>", hour);
+                }
+                out.push_str("</tr>\n");
+                for (weekday, day) in WEEKDAYS.iter().zip(&self.heatmap) {
+                    let _ = write!(out, "<tr><th>{}</th>", weekday);
+                    for &count in &day[hours.clone()] {
+                        let alpha = count as f64 / busiest as f64;
+                        let _ = write!(out, "<td style=\"background: rgba(100, 108, 255, {:.2})\">{}</td>", alpha, count);
+                    }
+                    out.push_str("</tr>\n");
+                }
+                out.push_str("</table>\n");
+            }
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+/// "1 h 5 min", "12 min" or "40 s".
+fn format_duration(ms: u64) -> String {
+    let minutes = ms / 60_000;
+    match minutes {
+        0 => format!("{} s", ms / 1000),
+        1..=59 => format!("{} min", minutes),
+        _ => format!("{} h {} min", minutes / 60, minutes % 60),
+    }
+}
+
+/// The configured report folder, or the default one in the user's documents.
+pub fn reports_dir(app: Option<&AppHandle>, settings: &ReportSettings) -> Option<PathBuf> {
+    if !settings.folder.is_empty() {
+        return Some(PathBuf::from(&settings.folder));
+    }
+    Some(app?.path().document_dir().ok()?.join(REPORTS_FOLDER))
+}
+
+/// Write `report` into `dir`, replacing an earlier report for the same week.
+pub fn write_report(dir: &Path, report: &WeeklyReport, format: ReportFormat) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    let (extension, contents) = match format {
+        ReportFormat::Markdown => ("md", report.to_markdown()),
+        ReportFormat::Html => ("html", report.to_html()),
+    };
+    let path = dir.join(format!("{}{}.{}", FILE_PREFIX, report.week, extension));
+    fs::write(&path, contents).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+    Ok(path)
+}
+
+/// Let the user know a report is ready.
+pub fn show_report_window(app: &AppHandle) {
+    crate::scheduler::show_corner_window(app, "report", "/report", "Restie - Weekly Report");
+}
+
+/// The report for the latest week in `dir`.
+pub fn latest_report(dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name().and_then(|name| name.to_str()).is_some_and(|name| name.starts_with(FILE_PREFIX))
+        })
+        // ISO dates sort by name
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::HistoryEvent;
+    use crate::test_support::TempDir;
+    use chrono::Utc;
+
+    const MINUTE: u64 = 60 * 1000;
+
+    fn at(day: u32, hour: u32, minute: u32) -> u64 {
+        Utc.with_ymd_and_hms(2024, 6, day, hour, minute, 0).unwrap().timestamp_millis() as u64
+    }
+
+    fn taken(end: u64) -> HistoryRecord {
+        HistoryRecord {
+            duration_ms: Some(MINUTE),
+            ..HistoryRecord::new(end, HistoryEvent::BreakCompleted)
+        }
+    }
+
+    fn report() -> WeeklyReport {
+        let records = vec![
+            HistoryRecord::new(at(3, 9, 0), HistoryEvent::BreakScheduled),
+            taken(at(3, 9, 21)),
+            taken(at(3, 10, 1)),
+            HistoryRecord::new(at(3, 11, 0), HistoryEvent::BreakPostponed),
+            HistoryRecord::new(at(3, 11, 5), HistoryEvent::BreakSkipped),
+            taken(at(5, 10, 30)),
+            // The week after
+            taken(at(10, 9, 0)),
+        ];
+        WeeklyReport::new(&Utc, &records, NaiveDate::from_ymd_opt(2024, 6, 3).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn report_counts_breaks_by_weekday_and_hour() {
+        let report = report();
+        assert_eq!(report.heatmap[0][9], 1);
+        assert_eq!(report.heatmap[0][10], 1);
+        assert_eq!(report.heatmap[2][10], 1);
+        assert_eq!(report.heatmap.iter().flatten().sum::<u32>(), 3);
+        assert_eq!(report.hours(), Some(9..=10));
+    }
+
+    #[test]
+    fn markdown_report_has_figures_and_heatmap() {
+        let markdown = report().to_markdown();
+        assert!(markdown.starts_with("# Break report for the week of 3 June 2024\n"));
+        assert!(markdown.contains("| Breaks due | 4 |\n"));
+        assert!(markdown.contains("| Breaks taken | 3 (75%) |\n"));
+        assert!(markdown.contains("| Postpones (share of breaks due) | 1 (25%) |\n"));
+        assert!(markdown.contains("| Longest stretch without a break | 1 h 4 min |\n"));
+        assert!(markdown.contains("\n| | 09 | 10 |\n|---|---:|---:|\n| Mon | 1 | 1 |\n| Tue | · | · |\n"));
+    }
+
+    #[test]
+    fn reports_replace_their_week_and_the_latest_is_found() {
+        let dir = TempDir::new("reports");
+
+        let path = write_report(&dir, &report(), ReportFormat::Html).unwrap();
+        assert_eq!(path, dir.join("restie-week-2024-06-03.html"));
+        assert!(fs::read_to_string(&path).unwrap().contains("<h1>Break report for the week of 3 June 2024</h1>"));
+        let mut earlier = report();
+        earlier.week = NaiveDate::from_ymd_opt(2024, 5, 27).unwrap();
+        write_report(&dir, &earlier, ReportFormat::Markdown).unwrap();
+        write_report(&dir, &report(), ReportFormat::Html).unwrap();
+
+        assert_eq!(latest_report(&dir), Some(path));
+    }
+}
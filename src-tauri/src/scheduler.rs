@@ -1,265 +1,2644 @@
-use crate::models::{BreakSettings, BreakState, BreakType, SchedulerState};
+use crate::clock::{local_date, local_instant, Clock, ClockReading, SystemClock};
+use crate::idle::IdleSource;
+use crate::lifecycle::{BreakError, BreakPhase, LifecycleEvent, TransitionError};
+use crate::events::{BreakTick, SchedulerEvent};
+use crate::history::{HistoryEvent, HistoryRecord, Interruption};
+use crate::models::{
+    BreakSettings, BreakState, BreakType, EscalationStep, LongbreakSchedule, Pomodoro, PomodoroMode, Presentation,
+    SchedulerState,
+};
+use crate::report::WeeklyReport;
+use crate::snapshot::{ResumePlan, SchedulerSnapshot};
+use chrono::{Days, Local, NaiveDate};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::Duration;
 use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
+use tokio::sync::mpsc;
 
 #[derive(Clone)]
 pub struct BreakScheduler {
     settings: Arc<Mutex<BreakSettings>>,
     state: Arc<Mutex<BreakState>>,
     scheduler_state: Arc<Mutex<SchedulerState>>,
+    clock: Arc<dyn Clock>,
+    idle: Option<Arc<dyn IdleSource>>,
+    /// Longest idle time seen since the user went away for at least a microbreak.
+    away_ms: Arc<Mutex<Option<u64>>>,
+    /// When the idle source was last polled (clock ms).
+    last_idle_check: Arc<Mutex<Option<u64>>>,
+    /// Clock reading from the last suspend/clock-change check.
+    last_reading: Arc<Mutex<Option<ClockReading>>>,
     app_handle: Option<AppHandle>,
+    snapshot_path: Option<PathBuf>,
+    history_path: Option<PathBuf>,
+    /// Task labels of the latest pomodoros, most recent first.
+    recent_labels: Arc<Mutex<Vec<String>>>,
+    commands: Option<mpsc::UnboundedSender<SchedulerCommand>>,
 }
 
+/// Messages for the scheduler task. Each one cancels the task's current sleep.
+#[derive(Debug)]
+enum SchedulerCommand {
+    /// The deadline or pause state changed: recompute when to wake up.
+    Reschedule,
+    Shutdown,
+}
+
+/// How often the progress snapshot is refreshed while nothing else changes, so a
+/// crash still leaves an accurate "last seen running" time on disk.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often the idle source is asked how long the user has been away.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// In active-time mode, pauses in input shorter than this (reading, thinking) still
+/// count as active time.
+const ACTIVE_GRACE_MS: u64 = 30 * 1000;
+
+/// How often the clocks are compared to catch suspends and wall-clock changes.
+const CLOCK_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Drift between the clocks below this is timer jitter, not a suspend or time change.
+const CLOCK_SLACK_MS: u64 = 2000;
+
+/// How often the countdown is pushed to the break window.
+const BREAK_TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A fixed break at most this long after an interval break takes its place, rather
+/// than two breaks coming back to back.
+const FIXED_BREAK_MERGE_MS: u64 = 5 * 60 * 1000;
+
+/// Breaks due within this long of each other are due together: only the longest is
+/// shown, and it counts for the others.
+const COINCIDENT_BREAK_MS: u64 = 60 * 1000;
+
+/// Longest task label kept for a pomodoro.
+const MAX_POMODORO_LABEL_CHARS: usize = 100;
+
+/// How many recent task labels are kept for the tray.
+pub const RECENT_POMODORO_LABELS: usize = 3;
+
+/// `complete_break` is accepted this much before the break ends, to absorb timer skew
+/// between the webview and the backend.
+const COMPLETE_TOLERANCE_MS: u64 = 1000;
+
 impl BreakScheduler {
     pub fn new(settings: Arc<Mutex<BreakSettings>>) -> Self {
+        Self::with_clock(settings, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(settings: Arc<Mutex<BreakSettings>>, clock: Arc<dyn Clock>) -> Self {
         Self {
             settings: settings.clone(),
             state: Arc::new(Mutex::new(BreakState::default())),
-            scheduler_state: Arc::new(Mutex::new(SchedulerState {
-                scheduled_break_time: None,
-                current_break_type: None,
-                time_left_ms: None,
-            })),
+            scheduler_state: Arc::new(Mutex::new(SchedulerState::default())),
+            clock,
+            idle: None,
+            away_ms: Arc::new(Mutex::new(None)),
+            last_idle_check: Arc::new(Mutex::new(None)),
+            last_reading: Arc::new(Mutex::new(None)),
             app_handle: None,
+            snapshot_path: None,
+            history_path: None,
+            recent_labels: Arc::new(Mutex::new(Vec::new())),
+            commands: None,
+        }
+    }
+
+    /// Restore progress saved by a previous run and keep persisting to `path`.
+    pub fn restore(&mut self, path: PathBuf) {
+        if let Some(snapshot) = crate::snapshot::load_snapshot(&path) {
+            let now = self.now_ms();
+            let plan = crate::snapshot::resume_plan(&snapshot, now, &self.settings.lock().unwrap());
+            println!("Restoring scheduler snapshot from {:?}: {:?}", path, plan);
+
+            let mut state = self.state.lock().unwrap();
+            *state = snapshot.state;
+            // A break that was on screen (or about to be) is shown again by the task
+            if matches!(state.phase, BreakPhase::Active | BreakPhase::Warning) {
+                state.phase = BreakPhase::Scheduled;
+                Self::end_break(&mut state);
+            }
+            let mut scheduler_state = self.scheduler_state.lock().unwrap();
+            match plan {
+                ResumePlan::Keep(scheduled) => {
+                    scheduler_state.scheduled_break_time = Some(scheduled);
+                    scheduler_state.current_break_type = snapshot.current_break_type;
+                    scheduler_state.time_left_ms = Some(scheduled - now);
+                }
+                ResumePlan::DueNow => {
+                    scheduler_state.scheduled_break_time = Some(now);
+                    scheduler_state.current_break_type = snapshot.current_break_type;
+                    scheduler_state.time_left_ms = Some(0);
+                }
+                ResumePlan::Reschedule => {}
+                ResumePlan::FreshCycle => {
+                    state.break_type = None;
+                    Self::restart_longbreak_cycle(&mut state, now);
+                    state.kind_due_at.clear();
+                    state.pomodoros_since_long_break = 0;
+                    if let Some(session) = state.pomodoro.take() {
+                        self.record_pomodoro(HistoryEvent::PomodoroInterrupted, &session, None, now);
+                    }
+                }
+            }
+        }
+        self.snapshot_path = Some(path);
+    }
+
+    /// Append pomodoros (and later breaks) to the history file at `path`.
+    pub fn set_history_path(&mut self, path: PathBuf) {
+        self.history_path = Some(path);
+    }
+
+    /// Seed the recent task labels, e.g. from the history loaded at startup.
+    pub fn set_recent_labels(&mut self, labels: Vec<String>) {
+        *self.recent_labels.lock().unwrap() = labels;
+    }
+
+    /// Task labels of the latest pomodoros, most recent first.
+    pub fn recent_labels(&self) -> Vec<String> {
+        self.recent_labels.lock().unwrap().clone()
+    }
+
+    pub fn set_idle_source(&mut self, idle: Option<Arc<dyn IdleSource>>) {
+        match idle {
+            Some(ref idle) => println!("Idle detection: {}", idle.name()),
+            None => println!("Idle detection not available"),
         }
+        self.idle = idle;
     }
 
     pub fn set_app_handle(&mut self, handle: AppHandle) {
         println!("Setting app handle in scheduler");
         self.app_handle = Some(handle);
-        // Schedule first break after app handle is set
-        println!("Scheduling first break...");
-        self.schedule_next_break();
-        println!("First break scheduled");
-    }
-
-    fn schedule_next_break(&self) {
-        println!("schedule_next_break called");
-        let settings = self.settings.lock().unwrap();
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+        self.start_task();
 
-        let state = self.state.lock().unwrap();
-        let break_type = if state.microbreaks_since_longbreak + 1 >= settings.longbreak_interval_microbreaks {
-            BreakType::Longbreak
+        let restored = self.scheduler_state.lock().unwrap().scheduled_break_time.is_some();
+        let dormant = self.state.lock().unwrap().phase == BreakPhase::Disabled;
+        if restored || dormant {
+            println!("Resuming restored schedule");
+            self.state_changed();
+            self.notify(SchedulerCommand::Reschedule);
         } else {
-            BreakType::Microbreak
-        };
+            // Schedule first break after app handle is set
+            println!("Scheduling first break...");
+            self.schedule_next_break();
+            println!("First break scheduled");
+        }
+        self.check_working_hours();
+    }
 
-        let interval_ms = match break_type {
-            BreakType::Microbreak => settings.microbreak_interval_minutes * 60 * 1000,
-            BreakType::Longbreak => settings.longbreak_duration_minutes * 60 * 1000,
-        } as u64;
+    /// Spawn the single long-lived scheduler task. It sleeps until the next break is
+    /// due and is woken through the command channel whenever the schedule changes.
+    fn start_task(&mut self) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.commands = Some(tx);
+        let scheduler = self.clone();
+        tauri::async_runtime::spawn(async move {
+            scheduler.run(rx).await;
+        });
+    }
 
-        let scheduled_time = now + interval_ms;
+    async fn run(self, mut commands: mpsc::UnboundedReceiver<SchedulerCommand>) {
+        println!("Scheduler task started");
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        let mut idle_poll = tokio::time::interval(IDLE_POLL_INTERVAL);
+        let mut clock_check = tokio::time::interval(CLOCK_CHECK_INTERVAL);
+        let mut break_tick = tokio::time::interval(BREAK_TICK_INTERVAL);
+        // Break the window was last shown for, so it fires once per deadline
+        let mut fired_for: Option<(BreakType, u64)> = None;
 
-        println!("Scheduling {:?} in {}ms (at timestamp {})", break_type, interval_ms, scheduled_time);
+        loop {
+            let wait = self.time_until_due(fired_for.as_ref());
+            let on_break = self.state.lock().unwrap().phase == BreakPhase::Active;
+            let due = async {
+                match wait {
+                    Some(wait) => tokio::time::sleep(wait).await,
+                    None => std::future::pending().await,
+                }
+            };
+            let report_wait = self.time_until_report();
+            let report_due = async {
+                match report_wait {
+                    Some(wait) => tokio::time::sleep(wait).await,
+                    None => std::future::pending().await,
+                }
+            };
 
-        let mut scheduler_state = self.scheduler_state.lock().unwrap();
-        scheduler_state.scheduled_break_time = Some(scheduled_time);
-        scheduler_state.current_break_type = Some(break_type.clone());
-        scheduler_state.time_left_ms = Some(interval_ms);
+            tokio::select! {
+                command = commands.recv() => match command {
+                    Some(SchedulerCommand::Reschedule) => continue,
+                    Some(SchedulerCommand::Shutdown) | None => break,
+                },
+                _ = due => {
+                    // Woken by a deadline that a suspend or time change has since moved
+                    if self.check_clock() || self.check_working_hours() {
+                        continue;
+                    }
+                    if self.state.lock().unwrap().phase == BreakPhase::Paused {
+                        self.end_timed_pause();
+                        continue;
+                    }
+                    fired_for = self.upcoming_break();
+                    self.trigger_break();
+                }
+                _ = report_due => self.write_due_report(),
+                _ = heartbeat.tick() => self.persist(),
+                _ = idle_poll.tick() => self.check_idle(),
+                _ = clock_check.tick() => {
+                    self.check_clock();
+                    self.check_working_hours();
+                }
+                _ = break_tick.tick(), if on_break => self.tick_break(),
+            }
+        }
 
-        drop(scheduler_state);
-        drop(settings);
+        println!("Scheduler task stopped");
+    }
+
+    /// Time left until the warning before the scheduled break (or the end of the
+    /// warning, or of a timed pause), or `None` if the task has nothing to wait for (not
+    /// scheduled, user away, nothing scheduled, or this deadline already fired).
+    fn time_until_due(&self, fired_for: Option<&(BreakType, u64)>) -> Option<Duration> {
+        let warning_ms = self.settings.lock().unwrap().warning_seconds as u64 * 1000;
+        let state = self.state.lock().unwrap();
+        match state.phase {
+            BreakPhase::Scheduled => {}
+            BreakPhase::Warning => {
+                let ends_at = state.warning_ends_at?;
+                return Some(Duration::from_millis(ends_at.saturating_sub(self.now_ms())));
+            }
+            // A timed pause wakes the task when it ends
+            BreakPhase::Paused => {
+                let until = state.paused_until?;
+                return Some(Duration::from_millis(until.saturating_sub(self.now_ms())));
+            }
+            _ => return None,
+        }
         drop(state);
+        // No point showing a break to someone who isn't there; re-checked on return
+        if self.away_ms.lock().unwrap().is_some() {
+            return None;
+        }
+        let upcoming = self.upcoming_break()?;
+        if fired_for == Some(&upcoming) {
+            return None;
+        }
+        Some(Duration::from_millis(upcoming.1.saturating_sub(warning_ms).saturating_sub(self.now_ms())))
+    }
 
-        // Start background task to check for break time
-        self.start_background_check();
+    /// The break that comes next and when: the scheduled interval or fixed break, or the
+    /// break after the running pomodoro or of a break kind if that comes first.
+    fn upcoming_break(&self) -> Option<(BreakType, u64)> {
+        let settings = self.settings.lock().unwrap().clone();
+        let state = self.state.lock().unwrap();
+        let scheduler_state = self.scheduler_state.lock().unwrap();
+        Self::pick_upcoming(&settings, &state, &scheduler_state)
     }
 
-    fn start_background_check(&self) {
-        let state = self.state.clone();
-        let scheduler_state = self.scheduler_state.clone();
-        let settings = self.settings.clone();
-        let app_handle = self.app_handle.clone();
+    /// Of the breaks due within `COINCIDENT_BREAK_MS` of the earliest one, the longest
+    /// wins (the scheduled break, then the first kind listed, on a tie).
+    fn pick_upcoming(
+        settings: &BreakSettings,
+        state: &BreakState,
+        scheduler_state: &SchedulerState,
+    ) -> Option<(BreakType, u64)> {
+        let scheduled = scheduler_state.current_break_type.clone().zip(scheduler_state.scheduled_break_time);
+        let pomodoro = state
+            .pomodoro
+            .as_ref()
+            .map(|session| (Self::pomodoro_break(settings, state), session.ends_at));
+        let kinds = settings
+            .break_kinds
+            .iter()
+            .filter_map(|kind| Some((BreakType::Custom(kind.name.clone()), *state.kind_due_at.get(&kind.name)?)));
+        let candidates: Vec<(BreakType, u64)> = scheduled.into_iter().chain(pomodoro).chain(kinds).collect();
+        let earliest = candidates.iter().map(|&(_, at)| at).min()?;
+        candidates
+            .into_iter()
+            .filter(|&(_, at)| at <= earliest + COINCIDENT_BREAK_MS)
+            .rev()
+            .max_by_key(|(break_type, _)| break_type.duration_ms(settings))
+    }
 
-        std::thread::spawn(move || {
-            let mut last_break_triggered = false;
+    /// Whether the interval to the next break only accumulates active time.
+    fn counts_active_time(&self) -> bool {
+        self.idle.is_some() && self.settings.lock().unwrap().count_active_time_only
+    }
 
-            loop {
-                std::thread::sleep(Duration::from_millis(500));
+    /// Poll the idle source. Being away for at least a microbreak counts as a natural
+    /// break once the user is back. In active-time mode, inactive time since the last
+    /// poll pushes the deadline out.
+    fn check_idle(&self) {
+        let Some(idle_ms) = self.idle.as_ref().and_then(|idle| idle.idle_ms()) else {
+            return;
+        };
+        let now = self.now_ms();
+        let since_last_check = self.last_idle_check.lock().unwrap().replace(now).map(|last| now.saturating_sub(last));
+        let microbreak_ms = self.settings.lock().unwrap().microbreak_duration_seconds as u64 * 1000;
 
-                let s = state.lock().unwrap();
-                if s.is_paused {
-                    drop(s);
-                    continue;
-                }
+        if idle_ms >= ACTIVE_GRACE_MS && self.counts_active_time() {
+            if let Some(elapsed) = since_last_check {
+                self.extend_deadline(elapsed.min(idle_ms));
+            }
+        }
 
-                let now = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_millis() as u64;
-
-                let ss = scheduler_state.lock().unwrap();
-
-                if let Some(scheduled_time) = ss.scheduled_break_time {
-                    let time_left = scheduled_time.saturating_sub(now);
-
-                    // Trigger break when time is up (within 1 second window)
-                    if time_left <= 500 && !last_break_triggered {
-                        // Time for break!
-                        println!("Break time reached! Showing break window... (time_left: {}ms)", time_left);
-
-                        // Mark break as triggered
-                        last_break_triggered = true;
-
-                        // Get fullscreen setting
-                        let fullscreen = settings.lock().unwrap().fullscreen_breaks;
-
-                        if let Some(ref app) = app_handle {
-                            // Check if break window already exists
-                            if let Some(window) = app.get_webview_window("break") {
-                                println!("Break window exists, showing it");
-                                let _ = window.show();
-                                let _ = window.set_focus();
-                            } else {
-                                println!("Creating new break window (fullscreen: {})", fullscreen);
-                                // Create new break window
-                                let builder = WebviewWindowBuilder::new(
-                                    app,
-                                    "break",
-                                    WebviewUrl::App("/break".into())
-                                )
-                                .title("Restie - Break Time")
-                                .decorations(false)
-                                .skip_taskbar(true);
-
-                                let builder = if fullscreen {
-                                    // Use maximized instead of native fullscreen for frameless look
-                                    // Don't use always_on_top so menu bar remains accessible
-                                    builder
-                                        .maximized(true)
-                                        .resizable(false)
-                                } else {
-                                    builder
-                                        .inner_size(800.0, 600.0)
-                                        .center()
-                                        .always_on_top(true)
-                                };
-
-                                match builder.build() {
-                                    Ok(_) => println!("Break window created successfully"),
-                                    Err(e) => println!("Failed to create break window: {:?}", e),
-                                }
-                            }
-                        } else {
-                            println!("No app handle available");
-                        }
-
-                        drop(ss);
-                        drop(s);
-                        continue;
-                    } else if time_left > 500 {
-                        // Reset the trigger flag when we're not near break time
-                        last_break_triggered = false;
-                    }
-                }
+        let mut away_ms = self.away_ms.lock().unwrap();
+        if idle_ms >= microbreak_ms {
+            *away_ms = Some(away_ms.unwrap_or(0).max(idle_ms));
+            return;
+        }
+        let Some(away) = away_ms.take() else {
+            return;
+        };
+        drop(away_ms);
+
+        self.finish_natural_break(away, Interruption::Idle);
+    }
+
+    /// Compare the clocks with the last check. A suspend of at least a microbreak counts
+    /// as a break taken; a wall-clock change moves the deadline with it, so the time left
+    /// stays the same. Returns whether the schedule was touched.
+    fn check_clock(&self) -> bool {
+        let reading = self.clock.read();
+        let Some(previous) = self.last_reading.lock().unwrap().replace(reading) else {
+            return false;
+        };
+        let (suspended_ms, jump_ms) = reading.since(&previous);
+        let mut changed = false;
 
-                drop(ss);
-                drop(s);
+        if jump_ms.unsigned_abs() > CLOCK_SLACK_MS {
+            println!("Wall clock moved by {}ms: shifting the schedule", jump_ms);
+            let mut scheduler_state = self.scheduler_state.lock().unwrap();
+            // A fixed break is tied to the wall clock and stays put
+            let fixed = matches!(scheduler_state.current_break_type, Some(BreakType::Fixed(_)));
+            if let Some(scheduled) = scheduler_state.scheduled_break_time.filter(|_| !fixed) {
+                scheduler_state.scheduled_break_time = Some(scheduled.saturating_add_signed(jump_ms));
             }
-        });
+            drop(scheduler_state);
+            let mut state = self.state.lock().unwrap();
+            state.kind_due_at.values_mut().for_each(|at| *at = at.saturating_add_signed(jump_ms));
+            if let Some(session) = state.pomodoro.as_mut() {
+                session.started_at = session.started_at.saturating_add_signed(jump_ms);
+                session.ends_at = session.ends_at.saturating_add_signed(jump_ms);
+            }
+            state.break_started_at = state.break_started_at.map(|t| t.saturating_add_signed(jump_ms));
+            state.break_ends_at = state.break_ends_at.map(|t| t.saturating_add_signed(jump_ms));
+            state.warning_ends_at = state.warning_ends_at.map(|t| t.saturating_add_signed(jump_ms));
+            changed = true;
+        }
+
+        if suspended_ms > CLOCK_SLACK_MS {
+            println!("System was suspended for {}ms", suspended_ms);
+            let microbreak_ms = self.settings.lock().unwrap().microbreak_duration_seconds as u64 * 1000;
+            let phase = self.state.lock().unwrap().phase;
+            if phase == BreakPhase::Active {
+                // The break kept running while asleep; finish it if it's over
+                self.tick_break();
+            } else if suspended_ms >= microbreak_ms {
+                self.finish_natural_break(suspended_ms, Interruption::Suspend);
+            } else if self.counts_active_time() {
+                self.extend_deadline(suspended_ms);
+            }
+            changed = true;
+        }
+
+        if changed {
+            // Idle time measured across the gap is meaningless
+            *self.last_idle_check.lock().unwrap() = None;
+            self.away_ms.lock().unwrap().take();
+            self.emit_state();
+        }
+        changed
     }
 
-    pub fn start_break(&self) {
+    /// Go dormant outside working hours, and start over with a fresh cycle when a
+    /// working window opens. A break on screen or a user pause is left to run its
+    /// course. Returns whether the phase changed.
+    fn check_working_hours(&self) -> bool {
+        let now = self.now_ms();
+        let working = self.settings.lock().unwrap().working_hours.is_working_time(now);
+
         let mut state = self.state.lock().unwrap();
-        state.is_break_active = true;
+        match (state.phase, working) {
+            (BreakPhase::Scheduled | BreakPhase::Warning, false) => {
+                println!("Outside working hours, breaks are dormant");
+                if let Err(e) = Self::transition(&mut state, LifecycleEvent::Disable) {
+                    println!("Could not disable breaks: {}", e);
+                    return false;
+                }
+                state.kind_due_at.clear();
+                state.warning_ends_at = None;
+                state.strict = false;
+                if let Some(session) = state.pomodoro.take() {
+                    self.record_pomodoro(HistoryEvent::PomodoroInterrupted, &session, None, now);
+                }
+                drop(state);
+                self.close_warning_window();
+                self.clear_schedule();
+                self.state_changed();
+                self.notify(SchedulerCommand::Reschedule);
+                true
+            }
+            (BreakPhase::Disabled, true) => {
+                println!("Working hours started, scheduling a fresh cycle");
+                if let Err(e) = Self::transition(&mut state, LifecycleEvent::Enable) {
+                    println!("Could not enable breaks: {}", e);
+                    return false;
+                }
+                Self::restart_longbreak_cycle(&mut state, now);
+                state.kind_due_at.clear();
+                state.pomodoros_since_long_break = 0;
+                state.consecutive_dodges = 0;
+                state.escalation_level = 0;
+                drop(state);
+                self.schedule_next_break();
+                true
+            }
+            _ => false,
+        }
     }
 
-    pub fn pause(&self) {
-        let mut state = self.state.lock().unwrap();
-        state.is_paused = true;
+    /// Push the countdown to the break window, and complete the break once it runs out.
+    fn tick_break(&self) {
+        let state = self.state.lock().unwrap();
+        if state.phase != BreakPhase::Active {
+            return;
+        }
+        let (Some(started_at), Some(ends_at)) = (state.break_started_at, state.break_ends_at) else {
+            return;
+        };
+        let tick = BreakTick {
+            break_type: state.break_type.clone(),
+            started_at,
+            ends_at,
+            time_left_ms: ends_at.saturating_sub(self.now_ms()),
+        };
+        drop(state);
+
+        if let Some(ref app) = self.app_handle {
+            crate::events::emit_tick(app, &tick);
+        }
+        if tick.time_left_ms == 0 {
+            println!("Break time is up, completing it");
+            if let Err(e) = self.complete_break() {
+                println!("Could not complete break: {}", e);
+            }
+        }
     }
 
-    pub fn resume(&self) {
+    /// Move the scheduled break and the break kinds' timers out by `ms` of inactive
+    /// time. Fixed breaks keep their time of day, and an interval break pushed into one
+    /// merges with it.
+    fn extend_deadline(&self, ms: u64) {
+        let settings = self.settings.lock().unwrap().clone();
         let mut state = self.state.lock().unwrap();
-        state.is_paused = false;
+        if state.phase != BreakPhase::Scheduled {
+            return;
+        }
+        state.kind_due_at.values_mut().for_each(|at| *at += ms);
+        let mut scheduler_state = self.scheduler_state.lock().unwrap();
+        let (Some(break_type), Some(scheduled)) =
+            (scheduler_state.current_break_type.clone(), scheduler_state.scheduled_break_time)
+        else {
+            return;
+        };
+        if !matches!(break_type, BreakType::Fixed(_)) {
+            let (break_type, scheduled) =
+                Self::merge_fixed_break(&settings, &state, self.now_ms(), break_type, scheduled + ms);
+            scheduler_state.current_break_type = Some(break_type);
+            scheduler_state.scheduled_break_time = Some(scheduled);
+        }
+        drop(scheduler_state);
+        drop(state);
+        self.emit_state();
     }
 
-    pub fn skip_break(&self) {
+    /// Credit time away from the computer as a break, for the interval cycle and for
+    /// every break kind no longer than the absence.
+    fn finish_natural_break(&self, away_ms: u64, cause: Interruption) {
+        let settings = self.settings.lock().unwrap().clone();
+        let now = self.now_ms();
+
         let mut state = self.state.lock().unwrap();
-        state.is_break_active = false;
-        state.break_type = None;
-        state.skip_count += 1;
+        // A break on screen, paused or disabled breaks: nothing to credit
+        if state.phase != BreakPhase::Scheduled {
+            return;
+        }
+        println!("User was away for {}ms", away_ms);
+        let kinds_reset = Self::reset_kind_timers(&settings, &mut state, now, away_ms);
+        let upcoming = self.scheduler_state.lock().unwrap().current_break_type.clone();
+        let credited = Self::credit_interval_break(&settings, &mut state, upcoming.as_ref(), now, away_ms);
+        if credited || kinds_reset {
+            state.break_due_at = None;
+            self.record(HistoryRecord {
+                break_type: upcoming.filter(|_| credited),
+                duration_ms: Some(away_ms),
+                cause: Some(cause),
+                ..HistoryRecord::new(now, HistoryEvent::BreakInterrupted)
+            });
+        }
+        if !credited {
+            drop(state);
+            if kinds_reset {
+                self.state_changed();
+                self.notify(SchedulerCommand::Reschedule);
+            }
+            return;
+        }
+        state.natural_break_count += 1;
         drop(state);
 
-        // Schedule next break after skip
         self.schedule_next_break();
     }
 
-    pub fn postpone_break(&self) {
+    /// Let a break of `length_ms` other than the scheduled one (time away, a break kind)
+    /// stand in for the upcoming interval break: a long-break-length one restarts the
+    /// long-break cycle, a microbreak-length one replaces an upcoming microbreak.
+    /// Returns whether it did; the caller then schedules the next break.
+    fn credit_interval_break(
+        settings: &BreakSettings,
+        state: &mut BreakState,
+        upcoming: Option<&BreakType>,
+        now: u64,
+        length_ms: u64,
+    ) -> bool {
+        if length_ms >= BreakType::Longbreak.duration_ms(settings) {
+            Self::restart_longbreak_cycle(state, now);
+            true
+        } else if upcoming == Some(&BreakType::Microbreak) && length_ms >= BreakType::Microbreak.duration_ms(settings) {
+            state.microbreaks_since_longbreak += 1;
+            true
+        } else {
+            // Too short to stand in for the upcoming long break
+            false
+        }
+    }
+
+    /// Give every break kind a running timer, starting new ones from `now`, and drop
+    /// the timers of kinds that were removed.
+    fn sync_kind_timers(settings: &BreakSettings, state: &mut BreakState, now: u64) {
+        state.kind_due_at.retain(|name, _| settings.break_kind(name).is_some());
+        for kind in &settings.break_kinds {
+            state.kind_due_at.entry(kind.name.clone()).or_insert(now + kind.interval_ms());
+        }
+    }
+
+    /// Restart the timers of break kinds no longer than `covered_ms`: a break (or time
+    /// away) that long counts for them too. Returns whether any timer was restarted.
+    fn reset_kind_timers(settings: &BreakSettings, state: &mut BreakState, now: u64, covered_ms: u64) -> bool {
+        let mut reset = false;
+        for kind in &settings.break_kinds {
+            if BreakType::Custom(kind.name.clone()).duration_ms(settings) <= covered_ms {
+                state.kind_due_at.insert(kind.name.clone(), now + kind.interval_ms());
+                reset = true;
+            }
+        }
+        reset
+    }
+
+    fn notify(&self, command: SchedulerCommand) {
+        if let Some(ref commands) = self.commands {
+            // The task only goes away on shutdown, when there is nothing left to wake
+            let _ = commands.send(command);
+        }
+    }
+
+    /// Stop the scheduler task. Progress is flushed here rather than left to the
+    /// task, which may not get polled again once the app is exiting.
+    pub fn shutdown(&self) {
+        self.persist();
+        self.notify(SchedulerCommand::Shutdown);
+    }
+
+    /// Save progress and tell the windows about it.
+    fn state_changed(&self) {
+        self.persist();
+        self.emit_state();
+    }
+
+    fn emit_state(&self) {
+        if let Some(ref app) = self.app_handle {
+            let payload = SchedulerEvent {
+                state: self.get_state(),
+                schedule: self.get_scheduler_state(),
+            };
+            crate::tray::show_phase(app, payload.state.phase);
+            crate::events::emit_state_changed(app, &payload);
+        }
+    }
+
+    /// Write the current progress to disk (no-op until `restore` has set a path).
+    fn persist(&self) {
+        let Some(ref path) = self.snapshot_path else {
+            return;
+        };
+        let snapshot = SchedulerSnapshot {
+            saved_at: self.now_ms(),
+            state: self.get_state(),
+            scheduled_break_time: self.scheduler_state.lock().unwrap().scheduled_break_time,
+            current_break_type: self.scheduler_state.lock().unwrap().current_break_type.clone(),
+        };
+        if let Err(e) = crate::snapshot::save_snapshot(path, &snapshot) {
+            println!("Warning: failed to save scheduler snapshot: {}", e);
+        }
+    }
+
+    fn schedule_next_break(&self) {
+        println!("schedule_next_break called");
+        let settings = self.settings.lock().unwrap();
+        let now = self.now_ms();
+
         let mut state = self.state.lock().unwrap();
-        state.postpone_count += 1;
+        Self::sync_kind_timers(&settings, &mut state, now);
+        let next = Self::next_break(&settings, &mut state, now);
+        // A new break: its postpones start from zero
+        state.postpones_this_break = 0;
+        state.last_postponed_at = None;
+        drop(settings);
         drop(state);
 
-        // Reschedule for 5 minutes later
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+        let Some((break_type, scheduled_time)) = next else {
+            println!("No interval or fixed break to schedule");
+            self.clear_schedule();
+            self.state_changed();
+            self.notify(SchedulerCommand::Reschedule);
+            return;
+        };
+        let interval_ms = scheduled_time - now;
+
+        println!("Scheduling {:?} in {}ms (at timestamp {})", break_type, interval_ms, scheduled_time);
+        self.record(HistoryRecord {
+            break_type: Some(break_type.clone()),
+            due_at: Some(scheduled_time),
+            ..HistoryRecord::new(now, HistoryEvent::BreakScheduled)
+        });
+
         let mut scheduler_state = self.scheduler_state.lock().unwrap();
-        scheduler_state.scheduled_break_time = Some(now + (5 * 60 * 1000));
+        scheduler_state.scheduled_break_time = Some(scheduled_time);
+        scheduler_state.current_break_type = Some(break_type);
+        scheduler_state.time_left_ms = Some(interval_ms);
         drop(scheduler_state);
 
-        // Start background check again
-        self.start_background_check();
+        self.state_changed();
+
+        // Wake the scheduler task so it sleeps until the new deadline
+        self.notify(SchedulerCommand::Reschedule);
     }
 
-    pub fn complete_break(&self) {
-        let mut state = self.state.lock().unwrap();
-        state.is_break_active = false;
-        state.break_type = None;
-        state.break_number += 1;
+    /// Pick the type and time of the next break. Microbreaks come one interval apart;
+    /// a long break takes the place of the microbreak that would reach the cycle's
+    /// count, or (by minutes) of any microbreak that would land at or after it is due.
+    /// A fixed break coming first, or soon after, takes precedence over both. When
+    /// pomodoros replace the interval breaks, only fixed breaks are left to schedule.
+    fn next_break(settings: &BreakSettings, state: &mut BreakState, now: u64) -> Option<(BreakType, u64)> {
+        if settings.pomodoro.mode == PomodoroMode::Instead {
+            let after = now.max(state.last_fixed_break_at.unwrap_or(0));
+            return crate::fixed_breaks::next_fixed_break(&settings.fixed_breaks, after)
+                .map(|(fixed, at)| (BreakType::Fixed(fixed.name.clone()), at));
+        }
+        let (break_type, at) = Self::next_interval_break(settings, state, now);
+        Some(Self::merge_fixed_break(settings, state, now, break_type, at))
+    }
 
-        // Determine next break type
-        let settings = self.settings.lock().unwrap();
-        if state.microbreaks_since_longbreak + 1 >= settings.longbreak_interval_microbreaks {
-            state.microbreaks_since_longbreak = 0;
+    fn next_interval_break(settings: &BreakSettings, state: &mut BreakState, now: u64) -> (BreakType, u64) {
+        let microbreak_at = now + settings.microbreak_interval_minutes as u64 * 60 * 1000;
+        match settings.longbreak_schedule {
+            LongbreakSchedule::Microbreaks => {
+                if state.microbreaks_since_longbreak + 1 >= settings.longbreak_interval_microbreaks {
+                    (BreakType::Longbreak, microbreak_at)
+                } else {
+                    (BreakType::Microbreak, microbreak_at)
+                }
+            }
+            LongbreakSchedule::Minutes => {
+                let cycle_started_at = *state.longbreak_cycle_started_at.get_or_insert(now);
+                let longbreak_at = cycle_started_at + settings.longbreak_interval_minutes as u64 * 60 * 1000;
+                if longbreak_at > microbreak_at {
+                    (BreakType::Microbreak, microbreak_at)
+                } else if longbreak_at > now {
+                    (BreakType::Longbreak, longbreak_at)
+                } else {
+                    // Overdue (skipped or postponed): ask again one interval from now
+                    (BreakType::Longbreak, microbreak_at)
+                }
+            }
+        }
+    }
+
+    /// Replace a break due at `at` with the next fixed break if that comes before it or
+    /// within `FIXED_BREAK_MERGE_MS` after it.
+    fn merge_fixed_break(
+        settings: &BreakSettings,
+        state: &BreakState,
+        now: u64,
+        break_type: BreakType,
+        at: u64,
+    ) -> (BreakType, u64) {
+        let after = now.max(state.last_fixed_break_at.unwrap_or(0));
+        match crate::fixed_breaks::next_fixed_break(&settings.fixed_breaks, after) {
+            Some((fixed, fixed_at)) if fixed_at <= at + FIXED_BREAK_MERGE_MS => (BreakType::Fixed(fixed.name.clone()), fixed_at),
+            _ => (break_type, at),
+        }
+    }
+
+    /// Remember that the scheduled fixed break has been dealt with, if that's what
+    /// `break_type` is.
+    fn consume_fixed_break(&self, state: &mut BreakState, break_type: Option<&BreakType>) {
+        if matches!(break_type, Some(BreakType::Fixed(_))) {
+            state.last_fixed_break_at = self.scheduler_state.lock().unwrap().scheduled_break_time;
+        }
+    }
+
+    /// The break on screen, or else the one that comes next: what skipping or
+    /// postponing applies to.
+    fn target_break(settings: &BreakSettings, state: &BreakState, scheduler_state: &SchedulerState) -> Option<BreakType> {
+        if state.phase == BreakPhase::Active {
+            return state.break_type.clone();
+        }
+        Self::pick_upcoming(settings, state, scheduler_state).map(|(break_type, _)| break_type)
+    }
+
+    /// The break the running pomodoro ends in: a long one after every
+    /// `long_break_every`-th pomodoro.
+    fn pomodoro_break(settings: &BreakSettings, state: &BreakState) -> BreakType {
+        if state.pomodoros_since_long_break + 1 >= settings.pomodoro.long_break_every {
+            BreakType::PomodoroLongBreak
         } else {
-            state.microbreaks_since_longbreak += 1;
+            BreakType::PomodoroBreak
         }
-        drop(state);
-        drop(settings);
+    }
 
-        // Schedule next break after completion
-        self.schedule_next_break();
+    /// Count the running pomodoro as done, now that its break came.
+    fn finish_pomodoro(&self, settings: &BreakSettings, state: &mut BreakState, now: u64) {
+        let break_type = Self::pomodoro_break(settings, state);
+        let Some(session) = state.pomodoro.take() else {
+            return;
+        };
+        state.pomodoros_since_long_break = match break_type {
+            BreakType::PomodoroLongBreak => 0,
+            _ => state.pomodoros_since_long_break + 1,
+        };
+        self.record_pomodoro(HistoryEvent::PomodoroCompleted, &session, Some(break_type), now);
     }
 
-    pub fn reschedule_with_new_settings(&self) {
-        println!("Rescheduling breaks with new settings...");
+    fn record_pomodoro(&self, event: HistoryEvent, session: &Pomodoro, break_type: Option<BreakType>, now: u64) {
+        self.record(HistoryRecord {
+            break_type,
+            label: session.label.clone(),
+            // Time worked, not counting any overrun past the session's end
+            duration_ms: Some(now.min(session.ends_at).saturating_sub(session.started_at)),
+            ..HistoryRecord::new(now, event)
+        });
+    }
 
-        // Check if break is currently active
-        let state = self.state.lock().unwrap();
-        if state.is_break_active {
-            println!("Break currently active, will reschedule after completion");
-            drop(state);
-            return; // Option A: wait until break completes
+    /// Append to the break history (no-op until a history path is set).
+    fn record(&self, record: HistoryRecord) {
+        let Some(ref path) = self.history_path else {
+            return;
+        };
+        if let Err(e) = crate::history::append(path, &record) {
+            println!("Warning: failed to record break history: {}", e);
+        }
+    }
+
+    /// Note the current settings in the history, so records can be told apart by the
+    /// settings they happened under.
+    pub fn record_settings(&self) {
+        let settings = self.settings.lock().unwrap().clone();
+        self.record(HistoryRecord {
+            settings: Some(settings),
+            ..HistoryRecord::new(self.now_ms(), HistoryEvent::SettingsApplied)
+        });
+    }
+
+    /// When the next weekly report is due: the start of this week if last week's
+    /// hasn't been written yet, otherwise the start of the next. `None` with reports off.
+    fn report_due_at(&self) -> Option<u64> {
+        if !self.settings.lock().unwrap().reports.enabled || self.history_path.is_none() {
+            return None;
+        }
+        let week = crate::history::week_start(local_date(&Local, self.now_ms())?);
+        let this_week = local_instant(&Local, week, 0)?;
+        match self.state.lock().unwrap().reported_until {
+            Some(until) if until >= this_week => local_instant(&Local, week + Days::new(7), 0),
+            _ => Some(this_week),
+        }
+    }
+
+    fn time_until_report(&self) -> Option<Duration> {
+        let due_at = self.report_due_at()?;
+        Some(Duration::from_millis(due_at.saturating_sub(self.now_ms())))
+    }
+
+    /// Write last week's report if it is due, and let the user know if they asked to.
+    fn write_due_report(&self) {
+        let Some(due_at) = self.report_due_at().filter(|&due_at| due_at <= self.now_ms()) else {
+            return;
+        };
+        // Done for this week even if writing fails, rather than retrying in a loop
+        self.state.lock().unwrap().reported_until = Some(due_at);
+        self.persist();
+        let Some(week) = local_date(&Local, due_at).and_then(|date| date.checked_sub_days(Days::new(7))) else {
+            return;
+        };
+        match self.write_report(week) {
+            Ok(path) => {
+                println!("Weekly report written to {:?}", path);
+                let notify = self.settings.lock().unwrap().reports.notify;
+                if let (true, Some(app)) = (notify, &self.app_handle) {
+                    crate::report::show_report_window(app);
+                }
+            }
+            Err(e) => println!("Failed to write weekly report: {}", e),
+        }
+    }
+
+    /// Write the report for the week starting on `week` (a Monday) to the report folder.
+    pub fn write_report(&self, week: NaiveDate) -> Result<PathBuf, String> {
+        let settings = self.settings.lock().unwrap().reports.clone();
+        let history_path = self.history_path.as_ref().ok_or("There is no break history")?;
+        let dir = crate::report::reports_dir(self.app_handle.as_ref(), &settings).ok_or("No folder for reports")?;
+        let records = crate::history::load(history_path);
+        let report = WeeklyReport::new(&Local, &records, week).ok_or("Invalid report week")?;
+        crate::report::write_report(&dir, &report, settings.format)
+    }
+
+    /// Start a new long-break cycle, as after a long break.
+    fn restart_longbreak_cycle(state: &mut BreakState, now: u64) {
+        state.microbreaks_since_longbreak = 0;
+        state.longbreak_cycle_started_at = Some(now);
+    }
+
+    /// Warn about the upcoming break first if a warning is configured, and start it
+    /// once the warning is over.
+    fn trigger_break(&self) {
+        let warning_ms = self.settings.lock().unwrap().warning_seconds as u64 * 1000;
+        let due_at = self.upcoming_break().map(|(_, at)| at);
+        let mut state = self.state.lock().unwrap();
+        // Postponed breaks keep their first due time
+        if state.break_due_at.is_none() {
+            state.break_due_at = due_at;
         }
+        let phase = state.phase;
         drop(state);
+        if phase == BreakPhase::Scheduled && warning_ms > 0 {
+            self.warn(warning_ms);
+            return;
+        }
+        println!("Break time reached! Showing break window...");
+        if let Err(e) = self.start_break() {
+            println!("Not starting break: {}", e);
+        }
+    }
 
-        // Clear current schedule
-        {
-            let mut scheduler_state = self.scheduler_state.lock().unwrap();
-            scheduler_state.scheduled_break_time = None;
-            scheduler_state.current_break_type = None;
-            scheduler_state.time_left_ms = None;
-            println!("Cleared old schedule");
+    /// Announce the upcoming break `warning_ms` ahead, with a prompt to start it now or
+    /// postpone it. The prompt doesn't take focus, so typing carries on undisturbed.
+    fn warn(&self, warning_ms: u64) {
+        let settings = self.settings.lock().unwrap().clone();
+        let mut state = self.state.lock().unwrap();
+        if let Err(e) = Self::transition(&mut state, LifecycleEvent::Warn) {
+            println!("Not warning about break: {}", e);
+            return;
         }
+        let now = self.now_ms();
+        state.warning_ends_at = Some(now + warning_ms);
+        state.strict = self.is_strict(&settings, &state);
+        println!("Break in {}ms, warning the user", warning_ms);
+        drop(state);
+        self.record(HistoryRecord {
+            break_type: self.upcoming_break().map(|(break_type, _)| break_type),
+            due_at: Some(now + warning_ms),
+            ..HistoryRecord::new(now, HistoryEvent::BreakWarned)
+        });
+        self.state_changed();
+        // Wake the task so it sleeps until the warning is over
+        self.notify(SchedulerCommand::Reschedule);
 
-        // Schedule next break with new settings
-        self.schedule_next_break();
-        println!("Rescheduled with new settings");
+        if let Some(ref app) = self.app_handle {
+            show_warning_window(app);
+        }
     }
 
-    pub fn get_state(&self) -> BreakState {
-        self.state.lock().unwrap().clone()
+    /// Move the lifecycle to `event`'s target phase, or leave it untouched.
+    fn transition(state: &mut BreakState, event: LifecycleEvent) -> Result<(), TransitionError> {
+        state.phase = crate::lifecycle::transition(state.phase, event)?;
+        Ok(())
     }
 
-    pub fn get_scheduler_state(&self) -> SchedulerState {
-        self.scheduler_state.lock().unwrap().clone()
+    /// Forget the break that was on screen or being warned about.
+    fn end_break(state: &mut BreakState) {
+        state.warning_ends_at = None;
+        state.break_type = None;
+        state.break_started_at = None;
+        state.break_ends_at = None;
+        state.strict = false;
+        state.escalation.clear();
+    }
+
+    fn wants_fullscreen(settings: &BreakSettings, state: &BreakState) -> bool {
+        let fullscreen = match state.break_type {
+            // Break kinds bring their own presentation
+            Some(BreakType::Custom(ref name)) => {
+                settings.break_kind(name).is_some_and(|kind| kind.presentation == Presentation::Fullscreen)
+            }
+            _ => settings.fullscreen_breaks,
+        };
+        fullscreen || state.escalation.contains(&EscalationStep::Fullscreen)
+    }
+
+    /// Count a skip or postpone towards escalation.
+    fn dodged(settings: &BreakSettings, state: &mut BreakState) {
+        state.consecutive_dodges += 1;
+        state.escalation_level = settings.escalation_level(state.consecutive_dodges);
+    }
+
+    /// Whether the break on screen (or else the upcoming one) is strict.
+    fn is_strict(&self, settings: &BreakSettings, state: &BreakState) -> bool {
+        if state.phase == BreakPhase::Active {
+            return state.strict;
+        }
+        let level = settings.escalation_level(state.consecutive_dodges);
+        if settings.escalation_for(level).contains(&EscalationStep::Strict) {
+            return true;
+        }
+        let upcoming = Self::target_break(settings, state, &self.scheduler_state.lock().unwrap());
+        upcoming.is_some_and(|break_type| settings.is_strict(&break_type))
+    }
+
+    /// Start the scheduled break now and show the break window. The backend owns the
+    /// countdown: the break completes by itself at `break_ends_at`.
+    pub fn start_break(&self) -> Result<(), BreakError> {
+        let settings = self.settings.lock().unwrap().clone();
+        let now = self.now_ms();
+
+        let mut state = self.state.lock().unwrap();
+        Self::transition(&mut state, LifecycleEvent::Start)?;
+        state.warning_ends_at = None;
+        let upcoming = Self::pick_upcoming(&settings, &state, &self.scheduler_state.lock().unwrap());
+        state.break_type = upcoming.map(|(break_type, _)| break_type);
+        let break_type = state.break_type.clone();
+        self.consume_fixed_break(&mut state, break_type.as_ref());
+        let break_type = state.break_type.clone().unwrap_or(BreakType::Microbreak);
+
+        state.escalation_level = settings.escalation_level(state.consecutive_dodges);
+        state.escalation = settings.escalation_for(state.escalation_level).to_vec();
+        if !state.escalation.is_empty() {
+            println!("Escalation level {}: {:?}", state.escalation_level, state.escalation);
+        }
+
+        let mut duration_ms = break_type.duration_ms(&settings);
+        if break_type == BreakType::Microbreak && state.escalation.contains(&EscalationStep::LongerMicrobreaks) {
+            duration_ms += settings.escalation_extra_seconds as u64 * 1000;
+        }
+        state.break_started_at = Some(now);
+        state.break_ends_at = Some(now + duration_ms);
+        self.record(HistoryRecord {
+            break_type: Some(break_type.clone()),
+            duration_ms: Some(duration_ms),
+            delay_ms: Some(state.break_due_at.map_or(0, |due_at| now.saturating_sub(due_at))),
+            escalation_level: Some(state.escalation_level),
+            ..HistoryRecord::new(now, HistoryEvent::BreakStarted)
+        });
+        state.strict = settings.is_strict(&break_type) || state.escalation.contains(&EscalationStep::Strict);
+        let fullscreen = Self::wants_fullscreen(&settings, &state);
+        drop(state);
+        self.close_warning_window();
+        self.state_changed();
+        // Wake the task so it starts ticking
+        self.notify(SchedulerCommand::Reschedule);
+
+        match self.app_handle {
+            Some(ref app) => show_break_window(app, fullscreen),
+            None => println!("No app handle available"),
+        }
+        Ok(())
+    }
+
+    /// Pause breaks until `until` (Unix ms), or until resumed if `None`.
+    pub fn pause(&self, until: Option<u64>) -> Result<(), BreakError> {
+        let now = self.now_ms();
+        let mut state = self.state.lock().unwrap();
+        let next = crate::lifecycle::transition(state.phase, LifecycleEvent::Pause)?;
+        if until.is_some_and(|until| until <= now) {
+            return Err(BreakError::PauseEndInPast);
+        }
+        state.phase = next;
+        state.paused_until = until;
+        state.warning_ends_at = None;
+        state.strict = false;
+        drop(state);
+        self.close_warning_window();
+        self.state_changed();
+        self.notify(SchedulerCommand::Reschedule);
+        Ok(())
+    }
+
+    /// Resume breaks. A break that came due during the pause isn't sprung on the user;
+    /// the interval starts over instead.
+    pub fn resume(&self) -> Result<(), BreakError> {
+        let settings = self.settings.lock().unwrap().clone();
+        let now = self.now_ms();
+
+        let mut state = self.state.lock().unwrap();
+        Self::transition(&mut state, LifecycleEvent::Resume)?;
+        state.paused_until = None;
+        // Same for break kinds whose timer ran out meanwhile
+        for kind in &settings.break_kinds {
+            if state.kind_due_at.get(&kind.name).is_some_and(|&at| at <= now) {
+                state.kind_due_at.insert(kind.name.clone(), now + kind.interval_ms());
+            }
+        }
+        drop(state);
+
+        let overdue = self
+            .scheduler_state
+            .lock()
+            .unwrap()
+            .scheduled_break_time
+            .is_none_or(|scheduled| scheduled <= now);
+        if overdue {
+            self.schedule_next_break();
+        } else {
+            self.state_changed();
+            self.notify(SchedulerCommand::Reschedule);
+        }
+        Ok(())
+    }
+
+    /// Resume if a timed pause is over.
+    fn end_timed_pause(&self) {
+        let paused_until = self.state.lock().unwrap().paused_until;
+        if paused_until.is_some_and(|until| until <= self.now_ms()) {
+            println!("Timed pause is over, resuming breaks");
+            if let Err(e) = self.resume() {
+                println!("Could not resume breaks: {}", e);
+            }
+        }
+    }
+
+    pub fn skip_break(&self) -> Result<(), BreakError> {
+        let settings = self.settings.lock().unwrap().clone();
+        let now = self.now_ms();
+
+        let mut state = self.state.lock().unwrap();
+        let next = crate::lifecycle::transition(state.phase, LifecycleEvent::Skip)?;
+        if self.is_strict(&settings, &state) {
+            return Err(BreakError::Strict);
+        }
+        let skipped = Self::target_break(&settings, &state, &self.scheduler_state.lock().unwrap());
+        state.phase = next;
+        Self::end_break(&mut state);
+        state.break_due_at = None;
+        self.record(HistoryRecord {
+            break_type: skipped.clone(),
+            ..HistoryRecord::new(now, HistoryEvent::BreakSkipped)
+        });
+        self.consume_fixed_break(&mut state, skipped.as_ref());
+        state.skip_count += 1;
+        Self::dodged(&settings, &mut state);
+
+        if let Some(skipped @ (BreakType::Custom(_) | BreakType::PomodoroBreak | BreakType::PomodoroLongBreak)) = skipped {
+            // Only this kind's timer starts over, or the pomodoro ends without its break;
+            // the interval schedule stands
+            match skipped {
+                BreakType::Custom(name) => {
+                    if let Some(kind) = settings.break_kind(&name) {
+                        state.kind_due_at.insert(name, now + kind.interval_ms());
+                    }
+                }
+                _ => self.finish_pomodoro(&settings, &mut state, now),
+            }
+            state.postpones_this_break = 0;
+            state.last_postponed_at = None;
+            drop(state);
+            self.close_break_window();
+            self.close_warning_window();
+            self.state_changed();
+            self.notify(SchedulerCommand::Reschedule);
+            return Ok(());
+        }
+        drop(state);
+        self.close_break_window();
+        self.close_warning_window();
+
+        // Schedule next break after skip
+        self.schedule_next_break();
+        Ok(())
+    }
+
+    /// Push the upcoming (or current) break back by one of the configured durations,
+    /// within the per-break limit and cooldown.
+    pub fn postpone_break(&self, minutes: u32) -> Result<(), BreakError> {
+        let settings = self.settings.lock().unwrap().clone();
+        let now = self.now_ms();
+
+        let mut state = self.state.lock().unwrap();
+        let next = crate::lifecycle::transition(state.phase, LifecycleEvent::Postpone)?;
+        if self.is_strict(&settings, &state) {
+            return Err(BreakError::Strict);
+        }
+        if !settings.postpone_durations_minutes.contains(&minutes) {
+            return Err(BreakError::PostponeNotOffered { minutes });
+        }
+        if state.postpones_this_break >= settings.max_postpones_per_break {
+            return Err(BreakError::PostponeLimitReached { max: settings.max_postpones_per_break });
+        }
+        if let Some(available_at) = Self::postpone_available_at(&settings, &state) {
+            if now < available_at {
+                return Err(BreakError::PostponeCooldown { remaining_ms: available_at - now });
+            }
+        }
+        let mut scheduler_state = self.scheduler_state.lock().unwrap();
+        let target = Self::target_break(&settings, &state, &scheduler_state);
+        state.phase = next;
+        Self::end_break(&mut state);
+        state.postpone_count += 1;
+        state.postpones_this_break += 1;
+        Self::dodged(&settings, &mut state);
+        state.last_postponed_at = Some(now);
+
+        let later = now + minutes as u64 * 60 * 1000;
+        self.record(HistoryRecord {
+            break_type: target.clone(),
+            due_at: Some(later),
+            ..HistoryRecord::new(now, HistoryEvent::BreakPostponed)
+        });
+        if let Some(BreakType::Custom(name)) = target {
+            state.kind_due_at.insert(name, later);
+        } else if target.as_ref().is_some_and(BreakType::is_pomodoro) {
+            // Keep working on the pomodoro a little longer
+            if let Some(session) = state.pomodoro.as_mut() {
+                session.ends_at = later;
+            }
+        } else {
+            let upcoming = scheduler_state.current_break_type.clone().unwrap_or(BreakType::Microbreak);
+            let (break_type, scheduled) = match upcoming {
+                BreakType::Fixed(_) => (upcoming, later),
+                // Postponing past a fixed break runs into it
+                _ => Self::merge_fixed_break(&settings, &state, now, upcoming, later),
+            };
+            scheduler_state.current_break_type = Some(break_type);
+            scheduler_state.scheduled_break_time = Some(scheduled);
+        }
+        drop(scheduler_state);
+        drop(state);
+        self.close_break_window();
+        self.close_warning_window();
+
+        self.state_changed();
+        self.notify(SchedulerCommand::Reschedule);
+        Ok(())
+    }
+
+    fn postpone_available_at(settings: &BreakSettings, state: &BreakState) -> Option<u64> {
+        let last_postponed_at = state.last_postponed_at?;
+        Some(last_postponed_at + settings.postpone_cooldown_seconds as u64 * 1000)
+    }
+
+    /// Finish the break on screen. Refused while more than `COMPLETE_TOLERANCE_MS` of
+    /// it is left, so the webview can't cut a break short.
+    pub fn complete_break(&self) -> Result<(), BreakError> {
+        let settings = self.settings.lock().unwrap().clone();
+        let now = self.now_ms();
+
+        let mut state = self.state.lock().unwrap();
+        let next = crate::lifecycle::transition(state.phase, LifecycleEvent::Complete)?;
+        if let Some(ends_at) = state.break_ends_at {
+            if now + COMPLETE_TOLERANCE_MS < ends_at {
+                return Err(BreakError::TooEarly { remaining_ms: ends_at - now });
+            }
+        }
+        state.phase = next;
+        let break_type = state.break_type.clone();
+        let length_ms = break_type.as_ref().map_or(0, |break_type| break_type.duration_ms(&settings));
+        self.record(HistoryRecord {
+            break_type: break_type.clone(),
+            duration_ms: state.break_started_at.map(|started_at| now.saturating_sub(started_at)),
+            ..HistoryRecord::new(now, HistoryEvent::BreakCompleted)
+        });
+        state.break_due_at = None;
+        // A break counts for every break kind it's at least as long as
+        Self::reset_kind_timers(&settings, &mut state, now, length_ms);
+        // ... and for a pomodoro that ran out meanwhile
+        let pomodoro_over = state.pomodoro.as_ref().is_some_and(|session| session.ends_at <= now)
+            && length_ms >= Self::pomodoro_break(&settings, &state).duration_ms(&settings);
+        if break_type.as_ref().is_some_and(BreakType::is_pomodoro) || pomodoro_over {
+            self.finish_pomodoro(&settings, &mut state, now);
+        }
+        let reschedule = match break_type {
+            // A break kind or pomodoro break only moves the interval schedule if it's long
+            // enough to replace its next break
+            Some(BreakType::Custom(_) | BreakType::PomodoroBreak | BreakType::PomodoroLongBreak) => {
+                let upcoming = self.scheduler_state.lock().unwrap().current_break_type.clone();
+                Self::credit_interval_break(&settings, &mut state, upcoming.as_ref(), now, length_ms)
+            }
+            // A fixed break (lunch, ...) is at least as good as a long one
+            Some(BreakType::Longbreak | BreakType::Fixed(_)) => {
+                Self::restart_longbreak_cycle(&mut state, now);
+                true
+            }
+            _ => {
+                state.microbreaks_since_longbreak += 1;
+                true
+            }
+        };
+        Self::end_break(&mut state);
+        state.break_number += 1;
+        // Taking a break properly clears any escalation
+        state.consecutive_dodges = 0;
+        state.escalation_level = 0;
+        if !reschedule {
+            state.postpones_this_break = 0;
+            state.last_postponed_at = None;
+        }
+        drop(state);
+        self.close_break_window();
+
+        // Schedule next break after completion
+        if reschedule {
+            self.schedule_next_break();
+        } else {
+            self.state_changed();
+            self.notify(SchedulerCommand::Reschedule);
+        }
+        Ok(())
+    }
+
+    /// The break window went away. A strict break can't be dismissed by closing it,
+    /// so the window comes back.
+    pub fn break_window_closed(&self) {
+        let state = self.state.lock().unwrap();
+        if state.phase != BreakPhase::Active || !state.strict {
+            return;
+        }
+        drop(state);
+        let Some(app) = self.app_handle.clone() else {
+            return;
+        };
+
+        println!("Break window closed during a strict break, reopening it");
+        let scheduler = self.clone();
+        tauri::async_runtime::spawn(async move {
+            // Let the old window finish tearing down before reusing its label
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let settings = scheduler.settings.lock().unwrap().clone();
+            let state = scheduler.state.lock().unwrap().clone();
+            if state.phase == BreakPhase::Active {
+                show_break_window(&app, Self::wants_fullscreen(&settings, &state));
+            }
+        });
+    }
+
+    fn close_break_window(&self) {
+        if let Some(window) = self.app_handle.as_ref().and_then(|app| app.get_webview_window("break")) {
+            let _ = window.close();
+        }
+    }
+
+    fn close_warning_window(&self) {
+        if let Some(window) = self.app_handle.as_ref().and_then(|app| app.get_webview_window("warning")) {
+            let _ = window.close();
+        }
+    }
+
+    /// Start a pomodoro, optionally for a task `label`. Its break comes when the work
+    /// session is over.
+    pub fn start_pomodoro(&self, label: Option<String>) -> Result<(), BreakError> {
+        let settings = self.settings.lock().unwrap().clone();
+        let now = self.now_ms();
+
+        let mut state = self.state.lock().unwrap();
+        if settings.pomodoro.mode == PomodoroMode::Off {
+            return Err(BreakError::PomodoroOff);
+        }
+        if !matches!(state.phase, BreakPhase::Scheduled | BreakPhase::Warning) {
+            return Err(BreakError::PomodoroNotNow { phase: state.phase });
+        }
+        if state.pomodoro.is_some() {
+            return Err(BreakError::PomodoroRunning);
+        }
+        let label = label
+            .map(|label| label.trim().chars().take(MAX_POMODORO_LABEL_CHARS).collect::<String>())
+            .filter(|label| !label.is_empty());
+        let session = Pomodoro {
+            started_at: now,
+            ends_at: now + settings.pomodoro.work_minutes as u64 * 60 * 1000,
+            label,
+        };
+        println!("Starting pomodoro {:?} until {}", session.label, session.ends_at);
+        self.record(HistoryRecord {
+            label: session.label.clone(),
+            ..HistoryRecord::new(now, HistoryEvent::PomodoroStarted)
+        });
+        if let Some(ref label) = session.label {
+            let mut labels = self.recent_labels.lock().unwrap();
+            labels.retain(|recent| recent != label);
+            labels.insert(0, label.clone());
+            labels.truncate(RECENT_POMODORO_LABELS);
+        }
+        state.pomodoro = Some(session);
+        drop(state);
+        self.state_changed();
+        self.notify(SchedulerCommand::Reschedule);
+        Ok(())
+    }
+
+    /// Stop the running pomodoro early, e.g. because something came up. It's recorded
+    /// with the time worked but doesn't count towards the long break.
+    pub fn interrupt_pomodoro(&self) -> Result<(), BreakError> {
+        self.end_pomodoro(HistoryEvent::PomodoroInterrupted)
+    }
+
+    /// Throw the running pomodoro away, as if it never started.
+    pub fn void_pomodoro(&self) -> Result<(), BreakError> {
+        self.end_pomodoro(HistoryEvent::PomodoroVoided)
+    }
+
+    fn end_pomodoro(&self, event: HistoryEvent) -> Result<(), BreakError> {
+        let now = self.now_ms();
+        let mut state = self.state.lock().unwrap();
+        let session = state.pomodoro.take().ok_or(BreakError::NoPomodoro)?;
+        println!("Pomodoro {:?}: {:?}", session.label, event);
+        self.record_pomodoro(event, &session, None, now);
+        drop(state);
+        self.state_changed();
+        self.notify(SchedulerCommand::Reschedule);
+        Ok(())
+    }
+
+    pub fn reschedule_with_new_settings(&self) {
+        println!("Rescheduling breaks with new settings...");
+        self.record_settings();
+
+        let pomodoro_off = self.settings.lock().unwrap().pomodoro.mode == PomodoroMode::Off;
+        // Check if break is currently active (or announced)
+        let mut state = self.state.lock().unwrap();
+        if matches!(state.phase, BreakPhase::Active | BreakPhase::Warning) {
+            println!("Break currently active, will reschedule after completion");
+            drop(state);
+            return; // Option A: wait until break completes
+        }
+        if pomodoro_off {
+            if let Some(session) = state.pomodoro.take() {
+                println!("Pomodoro mode switched off, interrupting the running pomodoro");
+                self.record_pomodoro(HistoryEvent::PomodoroInterrupted, &session, None, self.now_ms());
+            }
+        }
+        // Dormant: the new working hours decide whether to wake up
+        if state.phase == BreakPhase::Disabled {
+            drop(state);
+            self.check_working_hours();
+            return;
+        }
+        drop(state);
+        // Break kinds start over with their new intervals as well
+        self.state.lock().unwrap().kind_due_at.clear();
+
+        self.clear_schedule();
+        println!("Cleared old schedule");
+
+        // Schedule next break with new settings
+        self.schedule_next_break();
+        println!("Rescheduled with new settings");
+        self.check_working_hours();
+    }
+
+    fn clear_schedule(&self) {
+        let mut scheduler_state = self.scheduler_state.lock().unwrap();
+        scheduler_state.scheduled_break_time = None;
+        scheduler_state.current_break_type = None;
+        scheduler_state.time_left_ms = None;
+    }
+
+    pub fn get_state(&self) -> BreakState {
+        self.state.lock().unwrap().clone()
+    }
+
+    pub fn get_scheduler_state(&self) -> SchedulerState {
+        let mut scheduler_state = self.scheduler_state.lock().unwrap().clone();
+        let now = self.now_ms();
+        scheduler_state.active_time_only = self.counts_active_time();
+
+        let settings = self.settings.lock().unwrap().clone();
+        let state = self.state.lock().unwrap();
+        // Report the break that actually comes next, which may be a break kind
+        let upcoming = Self::pick_upcoming(&settings, &state, &scheduler_state);
+        scheduler_state.current_break_type = upcoming.as_ref().map(|(break_type, _)| break_type.clone());
+        scheduler_state.scheduled_break_time = upcoming.map(|(_, at)| at);
+        scheduler_state.time_left_ms = scheduler_state.scheduled_break_time.map(|scheduled| scheduled.saturating_sub(now));
+        scheduler_state.postpones_left = settings.max_postpones_per_break.saturating_sub(state.postpones_this_break);
+        scheduler_state.postpone_available_at =
+            Self::postpone_available_at(&settings, &state).filter(|&available_at| available_at > now);
+        scheduler_state
+    }
+
+    /// Current time according to the scheduler's clock (Unix ms).
+    pub fn now_ms(&self) -> u64 {
+        self.clock.now_ms()
+    }
+}
+
+/// Show the break window, creating it if needed. Both fullscreen and windowed
+/// modes are frameless (overlay-like).
+pub fn show_break_window(app: &AppHandle, fullscreen: bool) {
+    // Check if break window already exists
+    if let Some(window) = app.get_webview_window("break") {
+        println!("Break window exists, showing it");
+        let _ = window.show();
+        let _ = window.set_focus();
+        return;
+    }
+
+    println!("Creating new break window (fullscreen: {})", fullscreen);
+    let builder = WebviewWindowBuilder::new(app, "break", WebviewUrl::App("/break".into()))
+        .title("Restie - Break Time")
+        .decorations(false)
+        .skip_taskbar(true);
+
+    let builder = if fullscreen {
+        // Use maximized instead of native fullscreen for frameless look
+        // Don't use always_on_top so menu bar remains accessible
+        builder
+            .maximized(true)
+            .resizable(false)
+    } else {
+        builder
+            .inner_size(800.0, 600.0)
+            .center()
+            .always_on_top(true)
+    };
+
+    match builder.build() {
+        Ok(_) => println!("Break window created successfully"),
+        Err(e) => println!("Failed to create break window: {:?}", e),
+    }
+}
+
+/// Show the prompt announcing a break.
+pub fn show_warning_window(app: &AppHandle) {
+    show_corner_window(app, "warning", "/warning", "Restie - Break Coming Up");
+}
+
+/// Show a small window in the top right corner that stays on top without taking focus.
+pub fn show_corner_window(app: &AppHandle, label: &str, route: &str, title: &str) {
+    if let Some(window) = app.get_webview_window(label) {
+        let _ = window.show();
+        return;
+    }
+
+    const WIDTH: f64 = 320.0;
+    const MARGIN: f64 = 24.0;
+    let builder = WebviewWindowBuilder::new(app, label, WebviewUrl::App(route.into()))
+        .title(title)
+        .inner_size(WIDTH, 120.0)
+        .decorations(false)
+        .resizable(false)
+        .always_on_top(true)
+        .focused(false)
+        .skip_taskbar(true);
+    let builder = match app.primary_monitor() {
+        Ok(Some(monitor)) => {
+            let width = monitor.size().width as f64 / monitor.scale_factor();
+            builder.position(width - WIDTH - MARGIN, MARGIN)
+        }
+        _ => builder.center(),
+    };
+
+    match builder.build() {
+        Ok(_) => println!("Window {} created", label),
+        Err(e) => println!("Failed to create window {}: {:?}", label, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::idle::FakeIdleSource;
+    use crate::test_support::TempDir;
+
+    const START: u64 = 1_700_000_000_000;
+    const MINUTE: u64 = 60 * 1000;
+
+    fn scheduler_with(settings: BreakSettings) -> (BreakScheduler, ManualClock) {
+        let clock = ManualClock::new(START);
+        let scheduler = BreakScheduler::with_clock(Arc::new(Mutex::new(settings)), Arc::new(clock.clone()));
+        scheduler.schedule_next_break();
+        (scheduler, clock)
+    }
+
+    fn scheduler() -> (BreakScheduler, ManualClock) {
+        scheduler_with(BreakSettings::default())
+    }
+
+    fn scheduler_with_idle() -> (BreakScheduler, ManualClock, FakeIdleSource) {
+        let (mut scheduler, clock) = scheduler();
+        let idle = FakeIdleSource::default();
+        scheduler.set_idle_source(Some(Arc::new(idle.clone())));
+        (scheduler, clock, idle)
+    }
+
+    /// Start the upcoming break and let it run to the end.
+    fn take_break(scheduler: &BreakScheduler, clock: &ManualClock) {
+        scheduler.start_break().unwrap();
+        let ends_at = scheduler.get_state().break_ends_at.unwrap();
+        clock.advance(Duration::from_millis(ends_at - clock.now_ms()));
+        scheduler.complete_break().unwrap();
+    }
+
+    /// Go idle for `duration` and come back, polling the idle source on both sides.
+    fn step_away(scheduler: &BreakScheduler, clock: &ManualClock, idle: &FakeIdleSource, duration: Duration) {
+        clock.advance(duration);
+        idle.set_idle(duration);
+        scheduler.check_idle();
+        idle.set_idle(Duration::ZERO);
+        scheduler.check_idle();
+    }
+
+    fn next_break(scheduler: &BreakScheduler) -> (Option<BreakType>, Option<u64>) {
+        let scheduler_state = scheduler.get_scheduler_state();
+        (scheduler_state.current_break_type, scheduler_state.scheduled_break_time)
+    }
+
+    #[test]
+    fn first_break_is_a_microbreak_one_interval_away() {
+        let (scheduler, _clock) = scheduler();
+
+        let (break_type, scheduled) = next_break(&scheduler);
+        assert_eq!(break_type, Some(BreakType::Microbreak));
+        assert_eq!(scheduled, Some(START + 20 * MINUTE));
+        assert_eq!(scheduler.get_scheduler_state().time_left_ms, Some(20 * MINUTE));
+    }
+
+    #[test]
+    fn every_nth_break_is_a_long_break() {
+        let (scheduler, clock) = scheduler();
+
+        let mut rotation = Vec::new();
+        for _ in 0..8 {
+            let (break_type, scheduled) = next_break(&scheduler);
+            rotation.push(break_type.unwrap());
+            clock.set(scheduled.unwrap());
+            take_break(&scheduler, &clock);
+        }
+
+        let longbreaks: Vec<usize> = rotation
+            .iter()
+            .enumerate()
+            .filter(|(_, break_type)| **break_type == BreakType::Longbreak)
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(longbreaks, vec![3, 7]);
+        assert_eq!(scheduler.get_state().break_number, 8);
+        assert_eq!(scheduler.get_state().microbreaks_since_longbreak, 0);
+    }
+
+    #[test]
+    fn completed_break_schedules_from_completion_time() {
+        let (scheduler, clock) = scheduler();
+
+        clock.advance(Duration::from_secs(20 * 60));
+        scheduler.start_break().unwrap();
+        clock.advance(Duration::from_secs(5 * 60));
+        scheduler.complete_break().unwrap();
+
+        assert_eq!(next_break(&scheduler).1, Some(START + 45 * MINUTE));
+    }
+
+    #[test]
+    fn postpone_moves_the_break_five_minutes_from_now() {
+        let (scheduler, clock) = scheduler();
+        let (break_type, scheduled) = next_break(&scheduler);
+
+        clock.set(scheduled.unwrap());
+        scheduler.start_break().unwrap();
+        scheduler.postpone_break(5).unwrap();
+
+        assert_eq!(next_break(&scheduler).1, Some(scheduled.unwrap() + 5 * MINUTE));
+        assert_eq!(next_break(&scheduler).0, break_type, "postponing keeps the break type");
+        assert_eq!(scheduler.get_state().postpone_count, 1);
+        assert_eq!(scheduler.get_state().break_number, 0);
+        assert_eq!(scheduler.get_state().phase, BreakPhase::Scheduled);
+    }
+
+    #[test]
+    fn skip_counts_and_schedules_the_next_break_without_advancing_the_cycle() {
+        let (scheduler, clock) = scheduler();
+
+        clock.advance(Duration::from_secs(20 * 60));
+        scheduler.start_break().unwrap();
+        scheduler.skip_break().unwrap();
+
+        let state = scheduler.get_state();
+        assert_eq!(state.skip_count, 1);
+        assert_eq!(state.break_number, 0);
+        assert_eq!(state.microbreaks_since_longbreak, 0);
+        assert_eq!(state.phase, BreakPhase::Scheduled);
+        assert_eq!(next_break(&scheduler).1, Some(START + 40 * MINUTE));
+    }
+
+    #[test]
+    fn paused_scheduler_has_nothing_to_wait_for() {
+        let (scheduler, clock) = scheduler();
+        // The task wakes up early to warn about the break
+        assert_eq!(scheduler.time_until_due(None), Some(Duration::from_secs(20 * 60 - 30)));
+
+        scheduler.pause(None).unwrap();
+        assert_eq!(scheduler.get_state().phase, BreakPhase::Paused);
+        assert_eq!(scheduler.time_until_due(None), None);
+
+        clock.advance(Duration::from_secs(5 * 60));
+        scheduler.resume().unwrap();
+        assert_eq!(scheduler.get_state().phase, BreakPhase::Scheduled);
+        assert_eq!(scheduler.time_until_due(None), Some(Duration::from_secs(15 * 60 - 30)));
+    }
+
+    #[test]
+    fn overdue_break_is_due_immediately_but_fires_once() {
+        let (scheduler, clock) = scheduler();
+        let scheduled = next_break(&scheduler).1;
+
+        clock.advance(Duration::from_secs(30 * 60));
+        assert_eq!(scheduler.time_until_due(None), Some(Duration::ZERO));
+        let fired_for = next_break(&scheduler).0.zip(scheduled);
+        assert_eq!(scheduler.time_until_due(fired_for.as_ref()), None);
+    }
+
+    #[test]
+    fn reschedule_applies_new_interval_from_now() {
+        let (scheduler, clock) = scheduler();
+
+        clock.advance(Duration::from_secs(5 * 60));
+        scheduler.settings.lock().unwrap().microbreak_interval_minutes = 10;
+        scheduler.reschedule_with_new_settings();
+
+        assert_eq!(next_break(&scheduler).1, Some(START + 15 * MINUTE));
+    }
+
+    #[test]
+    fn reschedule_waits_while_a_break_is_active() {
+        let (scheduler, clock) = scheduler();
+
+        clock.advance(Duration::from_secs(20 * 60));
+        scheduler.start_break().unwrap();
+        scheduler.settings.lock().unwrap().microbreak_interval_minutes = 10;
+        scheduler.reschedule_with_new_settings();
+        assert_eq!(next_break(&scheduler).1, Some(START + 20 * MINUTE));
+
+        clock.advance(Duration::from_secs(20));
+        scheduler.complete_break().unwrap();
+        assert_eq!(next_break(&scheduler).1, Some(START + 30 * MINUTE + 20 * 1000));
+    }
+
+    #[test]
+    fn completing_without_an_active_break_changes_nothing() {
+        let (scheduler, _clock) = scheduler();
+
+        let err = scheduler.complete_break().unwrap_err();
+        assert!(matches!(err, BreakError::Transition(e) if e.phase == BreakPhase::Scheduled));
+        assert_eq!(scheduler.get_state().break_number, 0);
+        assert_eq!(scheduler.get_state().microbreaks_since_longbreak, 0);
+    }
+
+    #[test]
+    fn paused_scheduler_rejects_starting_a_break() {
+        let (scheduler, _clock) = scheduler();
+
+        scheduler.pause(None).unwrap();
+        assert!(matches!(scheduler.start_break(), Err(BreakError::Transition(e)) if e.phase == BreakPhase::Paused));
+        assert!(matches!(scheduler.skip_break(), Err(BreakError::Transition(e)) if e.phase == BreakPhase::Paused));
+        assert_eq!(scheduler.get_state().skip_count, 0);
+    }
+
+    #[test]
+    fn starting_a_break_records_its_type() {
+        let (scheduler, _clock) = scheduler();
+
+        scheduler.start_break().unwrap();
+        let state = scheduler.get_state();
+        assert_eq!(state.phase, BreakPhase::Active);
+        assert_eq!(state.break_type, Some(BreakType::Microbreak));
+    }
+
+    #[test]
+    fn microbreak_length_idle_counts_as_a_natural_microbreak() {
+        let (scheduler, clock, idle) = scheduler_with_idle();
+
+        clock.advance(Duration::from_secs(10 * 60));
+        step_away(&scheduler, &clock, &idle, Duration::from_secs(30));
+
+        let state = scheduler.get_state();
+        assert_eq!(state.natural_break_count, 1);
+        assert_eq!(state.microbreaks_since_longbreak, 1);
+        assert_eq!(state.break_number, 0);
+        assert_eq!(next_break(&scheduler).1, Some(clock.now_ms() + 20 * MINUTE));
+    }
+
+    #[test]
+    fn short_idle_is_not_a_break() {
+        let (scheduler, clock, idle) = scheduler_with_idle();
+
+        step_away(&scheduler, &clock, &idle, Duration::from_secs(10));
+
+        assert_eq!(scheduler.get_state().natural_break_count, 0);
+        assert_eq!(next_break(&scheduler).1, Some(START + 20 * MINUTE));
+    }
+
+    #[test]
+    fn long_break_length_idle_restarts_the_long_break_cycle() {
+        let (scheduler, clock, idle) = scheduler_with_idle();
+        scheduler.state.lock().unwrap().microbreaks_since_longbreak = 2;
+
+        step_away(&scheduler, &clock, &idle, Duration::from_secs(6 * 60));
+
+        let state = scheduler.get_state();
+        assert_eq!(state.microbreaks_since_longbreak, 0);
+        assert_eq!(state.natural_break_count, 1);
+        assert_eq!(next_break(&scheduler).0, Some(BreakType::Microbreak));
+    }
+
+    #[test]
+    fn microbreak_length_idle_does_not_replace_a_long_break() {
+        let (scheduler, clock, idle) = scheduler_with_idle();
+        scheduler.state.lock().unwrap().microbreaks_since_longbreak = 3;
+        scheduler.schedule_next_break();
+        let before = next_break(&scheduler);
+        assert_eq!(before.0, Some(BreakType::Longbreak));
+
+        step_away(&scheduler, &clock, &idle, Duration::from_secs(60));
+
+        assert_eq!(next_break(&scheduler), before);
+        assert_eq!(scheduler.get_state().natural_break_count, 0);
+    }
+
+    #[test]
+    fn break_is_held_back_while_the_user_is_away() {
+        let (scheduler, clock, idle) = scheduler_with_idle();
+
+        clock.advance(Duration::from_secs(19 * 60 + 50));
+        idle.set_idle(Duration::from_secs(30));
+        scheduler.check_idle();
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(scheduler.time_until_due(None), None);
+
+        idle.set_idle(Duration::ZERO);
+        scheduler.check_idle();
+        assert_eq!(next_break(&scheduler).1, Some(clock.now_ms() + 20 * MINUTE));
+    }
+
+    #[test]
+    fn active_time_mode_does_not_count_inactive_time() {
+        let (scheduler, clock, idle) = scheduler_with_idle();
+        {
+            let mut settings = scheduler.settings.lock().unwrap();
+            settings.count_active_time_only = true;
+            settings.microbreak_duration_seconds = 120;
+        }
+
+        clock.advance(Duration::from_secs(60));
+        scheduler.check_idle();
+        clock.advance(Duration::from_secs(60));
+        idle.set_idle(Duration::from_secs(60));
+        scheduler.check_idle();
+        clock.advance(Duration::from_secs(30));
+        idle.set_idle(Duration::ZERO);
+        scheduler.check_idle();
+
+        let scheduler_state = scheduler.get_scheduler_state();
+        assert!(scheduler_state.active_time_only);
+        assert_eq!(scheduler_state.scheduled_break_time, Some(START + 21 * MINUTE));
+        assert_eq!(scheduler_state.time_left_ms, Some(18 * MINUTE + 30 * 1000));
+    }
+
+    #[test]
+    fn active_time_mode_ignores_short_pauses_in_input() {
+        let (scheduler, clock, idle) = scheduler_with_idle();
+        scheduler.settings.lock().unwrap().count_active_time_only = true;
+
+        scheduler.check_idle();
+        clock.advance(Duration::from_secs(5));
+        idle.set_idle(Duration::from_secs(15));
+        scheduler.check_idle();
+
+        assert_eq!(next_break(&scheduler).1, Some(START + 20 * MINUTE));
+    }
+
+    #[test]
+    fn wall_clock_mode_counts_inactive_time() {
+        let (scheduler, clock, idle) = scheduler_with_idle();
+        scheduler.settings.lock().unwrap().microbreak_duration_seconds = 120;
+
+        scheduler.check_idle();
+        clock.advance(Duration::from_secs(60));
+        idle.set_idle(Duration::from_secs(60));
+        scheduler.check_idle();
+
+        let scheduler_state = scheduler.get_scheduler_state();
+        assert!(!scheduler_state.active_time_only);
+        assert_eq!(scheduler_state.scheduled_break_time, Some(START + 20 * MINUTE));
+    }
+
+    #[test]
+    fn long_suspend_counts_as_a_break() {
+        let (scheduler, clock) = scheduler();
+        scheduler.check_clock();
+
+        clock.advance(Duration::from_secs(60));
+        clock.suspend(Duration::from_secs(30 * 60));
+        assert!(scheduler.check_clock());
+
+        let state = scheduler.get_state();
+        assert_eq!(state.natural_break_count, 1);
+        assert_eq!(state.microbreaks_since_longbreak, 0);
+        assert_eq!(next_break(&scheduler), (Some(BreakType::Microbreak), Some(clock.now_ms() + 20 * MINUTE)));
+    }
+
+    #[test]
+    fn suspend_during_a_break_completes_it() {
+        let (scheduler, clock) = scheduler();
+        scheduler.check_clock();
+        scheduler.start_break().unwrap();
+
+        clock.suspend(Duration::from_secs(60));
+        scheduler.check_clock();
+
+        let state = scheduler.get_state();
+        assert_eq!(state.phase, BreakPhase::Scheduled);
+        assert_eq!(state.microbreaks_since_longbreak, 1);
+    }
+
+    #[test]
+    fn short_suspend_keeps_the_schedule() {
+        let (scheduler, clock) = scheduler();
+        scheduler.check_clock();
+
+        clock.suspend(Duration::from_secs(5));
+        scheduler.check_clock();
+
+        assert_eq!(scheduler.get_state().natural_break_count, 0);
+        assert_eq!(next_break(&scheduler).1, Some(START + 20 * MINUTE));
+    }
+
+    #[test]
+    fn wall_clock_change_keeps_the_time_left() {
+        let (scheduler, clock) = scheduler();
+        scheduler.check_clock();
+
+        clock.advance(Duration::from_secs(60));
+        clock.set(START - 60 * MINUTE);
+        assert!(scheduler.check_clock());
+        assert_eq!(scheduler.get_scheduler_state().time_left_ms, Some(19 * MINUTE));
+
+        clock.set(START + 120 * MINUTE);
+        scheduler.check_clock();
+        assert_eq!(scheduler.get_scheduler_state().time_left_ms, Some(19 * MINUTE));
+        assert_eq!(scheduler.get_state().natural_break_count, 0);
+    }
+
+    #[test]
+    fn break_records_its_start_and_end() {
+        let (scheduler, clock) = scheduler_with(BreakSettings {
+            longbreak_interval_microbreaks: 1,
+            ..BreakSettings::default()
+        });
+        clock.advance(Duration::from_secs(60));
+
+        scheduler.start_break().unwrap();
+
+        let state = scheduler.get_state();
+        assert_eq!(state.break_type, Some(BreakType::Longbreak));
+        assert_eq!(state.break_started_at, Some(START + MINUTE));
+        assert_eq!(state.break_ends_at, Some(START + 6 * MINUTE));
+    }
+
+    #[test]
+    fn early_completion_is_rejected() {
+        let (scheduler, clock) = scheduler();
+        scheduler.start_break().unwrap();
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(scheduler.complete_break(), Err(BreakError::TooEarly { remaining_ms: 10 * 1000 }));
+        assert_eq!(scheduler.get_state().phase, BreakPhase::Active);
+
+        // Within the tolerance of the end
+        clock.advance(Duration::from_millis(9500));
+        scheduler.complete_break().unwrap();
+        assert_eq!(scheduler.get_state().break_number, 1);
+    }
+
+    #[test]
+    fn break_completes_itself_when_time_runs_out() {
+        let (scheduler, clock) = scheduler();
+        scheduler.start_break().unwrap();
+
+        clock.advance(Duration::from_secs(19));
+        scheduler.tick_break();
+        assert_eq!(scheduler.get_state().phase, BreakPhase::Active);
+
+        clock.advance(Duration::from_secs(1));
+        scheduler.tick_break();
+        let state = scheduler.get_state();
+        assert_eq!(state.phase, BreakPhase::Scheduled);
+        assert_eq!(state.break_number, 1);
+        assert_eq!(state.break_ends_at, None);
+    }
+
+    #[test]
+    fn long_break_follows_the_microbreak_interval_not_its_duration() {
+        let (scheduler, clock) = scheduler_with(BreakSettings {
+            longbreak_interval_microbreaks: 2,
+            longbreak_duration_minutes: 30,
+            ..BreakSettings::default()
+        });
+
+        clock.set(START + 20 * MINUTE);
+        take_break(&scheduler, &clock);
+
+        let after_microbreak = clock.now_ms();
+        assert_eq!(next_break(&scheduler), (Some(BreakType::Longbreak), Some(after_microbreak + 20 * MINUTE)));
+    }
+
+    #[test]
+    fn long_breaks_by_minutes_replace_the_microbreak_that_would_overrun() {
+        let (scheduler, clock) = scheduler_with(BreakSettings {
+            longbreak_schedule: LongbreakSchedule::Minutes,
+            longbreak_interval_minutes: 60,
+            ..BreakSettings::default()
+        });
+
+        let mut rotation = Vec::new();
+        for _ in 0..5 {
+            let (break_type, scheduled) = next_break(&scheduler);
+            rotation.push((break_type.unwrap(), (scheduled.unwrap() - START) / 1000));
+            clock.set(scheduled.unwrap());
+            take_break(&scheduler, &clock);
+        }
+
+        // Microbreaks 20 min after the previous break ends; the long break lands on the
+        // hour, and the next cycle starts when it ends
+        assert_eq!(
+            rotation,
+            vec![
+                (BreakType::Microbreak, 20 * 60),
+                (BreakType::Microbreak, 40 * 60 + 20),
+                (BreakType::Longbreak, 60 * 60),
+                (BreakType::Microbreak, 85 * 60),
+                (BreakType::Microbreak, 105 * 60 + 20),
+            ]
+        );
+        assert_eq!(scheduler.get_state().microbreaks_since_longbreak, 2);
+    }
+
+    #[test]
+    fn skipped_long_break_by_minutes_comes_back_one_interval_later() {
+        let (scheduler, clock) = scheduler_with(BreakSettings {
+            longbreak_schedule: LongbreakSchedule::Minutes,
+            microbreak_interval_minutes: 30,
+            longbreak_interval_minutes: 30 + 1,
+            ..BreakSettings::default()
+        });
+        clock.set(START + 30 * MINUTE);
+        take_break(&scheduler, &clock);
+        let (break_type, scheduled) = next_break(&scheduler);
+        assert_eq!((break_type, scheduled), (Some(BreakType::Longbreak), Some(START + 31 * MINUTE)));
+
+        clock.set(scheduled.unwrap());
+        scheduler.skip_break().unwrap();
+
+        assert_eq!(next_break(&scheduler), (Some(BreakType::Longbreak), Some(START + 61 * MINUTE)));
+    }
+
+    #[test]
+    fn postpone_uses_the_chosen_duration() {
+        let (scheduler, clock) = scheduler();
+        clock.set(START + 20 * MINUTE);
+        scheduler.start_break().unwrap();
+
+        scheduler.postpone_break(10).unwrap();
+        assert_eq!(next_break(&scheduler).1, Some(START + 30 * MINUTE));
+
+        assert_eq!(scheduler.postpone_break(7), Err(BreakError::PostponeNotOffered { minutes: 7 }));
+    }
+
+    #[test]
+    fn postpones_are_limited_per_break() {
+        let (scheduler, clock) = scheduler_with(BreakSettings {
+            max_postpones_per_break: 2,
+            postpone_cooldown_seconds: 0,
+            ..BreakSettings::default()
+        });
+
+        scheduler.postpone_break(5).unwrap();
+        assert_eq!(scheduler.get_scheduler_state().postpones_left, 1);
+        scheduler.postpone_break(5).unwrap();
+        assert_eq!(scheduler.get_scheduler_state().postpones_left, 0);
+        assert_eq!(scheduler.postpone_break(5), Err(BreakError::PostponeLimitReached { max: 2 }));
+
+        // The next break starts with a clean slate
+        clock.set(next_break(&scheduler).1.unwrap());
+        take_break(&scheduler, &clock);
+        assert_eq!(scheduler.get_scheduler_state().postpones_left, 2);
+        assert_eq!(scheduler.get_state().postpone_count, 2);
+    }
+
+    #[test]
+    fn postpone_cooldown_spaces_out_postpones() {
+        let (scheduler, clock) = scheduler();
+
+        scheduler.postpone_break(5).unwrap();
+        clock.advance(Duration::from_secs(20));
+        assert_eq!(
+            scheduler.get_scheduler_state().postpone_available_at,
+            Some(START + MINUTE)
+        );
+        assert_eq!(
+            scheduler.postpone_break(5),
+            Err(BreakError::PostponeCooldown { remaining_ms: 40 * 1000 })
+        );
+
+        clock.advance(Duration::from_secs(40));
+        scheduler.postpone_break(5).unwrap();
+        assert_eq!(scheduler.get_state().postpones_this_break, 2);
+    }
+
+    #[test]
+    fn strict_break_cannot_be_skipped_or_postponed() {
+        let (scheduler, clock) = scheduler_with(BreakSettings {
+            strict_microbreaks: true,
+            ..BreakSettings::default()
+        });
+
+        // Neither the upcoming break nor the one on screen
+        assert_eq!(scheduler.skip_break(), Err(BreakError::Strict));
+        scheduler.start_break().unwrap();
+        assert!(scheduler.get_state().strict);
+        assert_eq!(scheduler.skip_break(), Err(BreakError::Strict));
+        assert_eq!(scheduler.postpone_break(5), Err(BreakError::Strict));
+
+        let state = scheduler.get_state();
+        assert_eq!(state.phase, BreakPhase::Active);
+        assert_eq!((state.skip_count, state.postpone_count), (0, 0));
+
+        clock.advance(Duration::from_secs(20));
+        scheduler.complete_break().unwrap();
+        assert!(!scheduler.get_state().strict);
+    }
+
+    #[test]
+    fn strict_mode_is_set_per_break_type() {
+        let (scheduler, _clock) = scheduler_with(BreakSettings {
+            strict_longbreaks: true,
+            ..BreakSettings::default()
+        });
+
+        scheduler.start_break().unwrap();
+        assert!(!scheduler.get_state().strict);
+        scheduler.skip_break().unwrap();
+    }
+
+    #[test]
+    fn repeated_dodging_escalates_the_next_break() {
+        let (scheduler, clock) = scheduler_with(BreakSettings {
+            escalation_after_dodges: 2,
+            postpone_cooldown_seconds: 0,
+            ..BreakSettings::default()
+        });
+
+        scheduler.skip_break().unwrap();
+        scheduler.postpone_break(5).unwrap();
+        assert_eq!(scheduler.get_state().escalation_level, 1);
+        scheduler.skip_break().unwrap();
+        scheduler.skip_break().unwrap();
+
+        scheduler.start_break().unwrap();
+        let state = scheduler.get_state();
+        assert_eq!(state.escalation_level, 2);
+        assert_eq!(state.escalation, vec![EscalationStep::Fullscreen, EscalationStep::LongerMicrobreaks]);
+        assert!(!state.strict);
+        assert_eq!(state.break_ends_at, Some(clock.now_ms() + 40 * 1000));
+    }
+
+    #[test]
+    fn top_escalation_makes_breaks_strict_until_one_is_taken() {
+        let (scheduler, clock) = scheduler_with(BreakSettings {
+            escalation_after_dodges: 1,
+            escalation_steps: vec![EscalationStep::Strict],
+            ..BreakSettings::default()
+        });
+
+        scheduler.skip_break().unwrap();
+        assert_eq!(scheduler.skip_break(), Err(BreakError::Strict));
+
+        scheduler.start_break().unwrap();
+        assert!(scheduler.get_state().strict);
+        clock.advance(Duration::from_secs(20));
+        scheduler.complete_break().unwrap();
+
+        let state = scheduler.get_state();
+        assert_eq!((state.consecutive_dodges, state.escalation_level), (0, 0));
+        assert!(state.escalation.is_empty());
+        scheduler.skip_break().unwrap();
+    }
+
+    #[test]
+    fn break_is_announced_before_it_starts() {
+        let (scheduler, clock) = scheduler();
+        clock.set(START + 20 * MINUTE - 30 * 1000);
+        scheduler.trigger_break();
+
+        let state = scheduler.get_state();
+        assert_eq!(state.phase, BreakPhase::Warning);
+        assert_eq!(state.warning_ends_at, Some(START + 20 * MINUTE));
+        assert_eq!(scheduler.time_until_due(None), Some(Duration::from_secs(30)));
+
+        clock.set(START + 20 * MINUTE);
+        scheduler.trigger_break();
+        let state = scheduler.get_state();
+        assert_eq!(state.phase, BreakPhase::Active);
+        assert_eq!(state.warning_ends_at, None);
+        assert_eq!(state.break_type, Some(BreakType::Microbreak));
+    }
+
+    #[test]
+    fn announced_break_can_be_postponed_or_started_early() {
+        let (scheduler, clock) = scheduler();
+        clock.set(START + 20 * MINUTE - 30 * 1000);
+        scheduler.trigger_break();
+        scheduler.postpone_break(5).unwrap();
+        assert_eq!(scheduler.get_state().phase, BreakPhase::Scheduled);
+        assert_eq!(scheduler.get_state().warning_ends_at, None);
+        assert_eq!(next_break(&scheduler).1, Some(clock.now_ms() + 5 * MINUTE));
+
+        clock.advance(Duration::from_secs(5 * 60 - 30));
+        scheduler.trigger_break();
+        scheduler.start_break().unwrap();
+        assert_eq!(scheduler.get_state().phase, BreakPhase::Active);
+        assert_eq!(scheduler.get_state().warning_ends_at, None);
+    }
+
+    #[test]
+    fn announced_strict_break_says_so_and_cannot_be_postponed() {
+        let (scheduler, clock) = scheduler_with(BreakSettings {
+            strict_microbreaks: true,
+            ..BreakSettings::default()
+        });
+        clock.set(START + 20 * MINUTE - 30 * 1000);
+        scheduler.trigger_break();
+        assert!(scheduler.get_state().strict);
+        assert_eq!(scheduler.postpone_break(5), Err(BreakError::Strict));
+
+        scheduler.pause(None).unwrap();
+        assert!(!scheduler.get_state().strict);
+    }
+
+    #[test]
+    fn breaks_start_straight_away_without_a_warning_period() {
+        let (scheduler, clock) = scheduler_with(BreakSettings {
+            warning_seconds: 0,
+            ..BreakSettings::default()
+        });
+        assert_eq!(scheduler.time_until_due(None), Some(Duration::from_secs(20 * 60)));
+
+        clock.set(START + 20 * MINUTE);
+        scheduler.trigger_break();
+        assert_eq!(scheduler.get_state().phase, BreakPhase::Active);
+    }
+
+    #[test]
+    fn break_events_are_recorded_with_how_late_the_break_was() {
+        let (mut scheduler, clock) = scheduler_with(BreakSettings {
+            warning_seconds: 0,
+            ..BreakSettings::default()
+        });
+        let dir = TempDir::new("break-events");
+        let history = crate::history::history_path(&dir);
+        scheduler.set_history_path(history.clone());
+
+        clock.set(START + 20 * MINUTE);
+        scheduler.trigger_break();
+        scheduler.postpone_break(5).unwrap();
+        clock.advance(Duration::from_secs(5 * 60));
+        scheduler.trigger_break();
+        let ends_at = scheduler.get_state().break_ends_at.unwrap();
+        clock.set(ends_at);
+        scheduler.complete_break().unwrap();
+
+        let records = crate::history::load(&history);
+        let events: Vec<_> = records.iter().map(|record| record.event).collect();
+        assert_eq!(
+            events,
+            vec![
+                HistoryEvent::BreakStarted,
+                HistoryEvent::BreakPostponed,
+                HistoryEvent::BreakStarted,
+                HistoryEvent::BreakCompleted,
+                HistoryEvent::BreakScheduled,
+            ]
+        );
+        assert_eq!(records[0].delay_ms, Some(0));
+        assert_eq!(records[1].due_at, Some(START + 25 * MINUTE));
+        assert_eq!(records[2].delay_ms, Some(5 * MINUTE));
+        assert_eq!(records[3].duration_ms, Some(20 * 1000));
+        assert_eq!(scheduler.get_state().break_due_at, None);
+    }
+
+    #[test]
+    fn weekly_report_is_written_once_a_week() {
+        let dir = TempDir::new("weekly-report");
+        let (mut scheduler, clock) = scheduler_with(BreakSettings {
+            reports: crate::models::ReportSettings {
+                enabled: true,
+                folder: dir.to_string_lossy().into_owned(),
+                ..crate::models::ReportSettings::default()
+            },
+            ..BreakSettings::default()
+        });
+        assert_eq!(scheduler.time_until_report(), None);
+        scheduler.set_history_path(crate::history::history_path(&dir));
+
+        // Last week's report is due as soon as reports are on
+        assert_eq!(scheduler.time_until_report(), Some(Duration::ZERO));
+        scheduler.write_due_report();
+        let week = crate::history::week_start(local_date(&Local, START).unwrap());
+        let last_week = week - Days::new(7);
+        assert!(dir.join(format!("restie-week-{}.md", last_week)).exists());
+
+        let next_week = local_instant(&Local, week + Days::new(7), 0).unwrap();
+        assert_eq!(scheduler.time_until_report(), Some(Duration::from_millis(next_week - START)));
+        clock.set(next_week);
+        scheduler.write_due_report();
+        assert!(dir.join(format!("restie-week-{}.md", week)).exists());
+        assert_eq!(scheduler.get_state().reported_until, Some(next_week));
+    }
+
+    #[test]
+    fn timed_pause_ends_by_itself() {
+        let (scheduler, clock) = scheduler();
+
+        scheduler.pause(Some(START + 30 * MINUTE)).unwrap();
+        assert_eq!(scheduler.time_until_due(None), Some(Duration::from_secs(30 * 60)));
+
+        clock.advance(Duration::from_secs(10 * 60));
+        scheduler.end_timed_pause();
+        assert_eq!(scheduler.get_state().phase, BreakPhase::Paused);
+
+        clock.advance(Duration::from_secs(20 * 60));
+        scheduler.end_timed_pause();
+        let state = scheduler.get_state();
+        assert_eq!(state.phase, BreakPhase::Scheduled);
+        assert_eq!(state.paused_until, None);
+        // The break that came due during the pause starts over
+        assert_eq!(next_break(&scheduler).1, Some(START + 50 * MINUTE));
+    }
+
+    #[test]
+    fn pause_has_to_end_in_the_future() {
+        let (scheduler, _clock) = scheduler();
+
+        assert_eq!(scheduler.pause(Some(START)), Err(BreakError::PauseEndInPast));
+        assert_eq!(scheduler.get_state().phase, BreakPhase::Scheduled);
+    }
+
+    /// Working hours that don't depend on the test machine's timezone.
+    fn working_hours(always: bool) -> crate::working_hours::WorkingHours {
+        let day = if always {
+            vec![crate::working_hours::TimeRange {
+                start: "00:00".to_string(),
+                end: "24:00".to_string(),
+            }]
+        } else {
+            vec![]
+        };
+        crate::working_hours::WorkingHours {
+            enabled: true,
+            days: vec![day; 7],
+        }
+    }
+
+    #[test]
+    fn scheduler_is_dormant_outside_working_hours() {
+        let (scheduler, _clock) = scheduler_with(BreakSettings {
+            working_hours: working_hours(false),
+            ..BreakSettings::default()
+        });
+
+        assert!(scheduler.check_working_hours());
+        assert_eq!(scheduler.get_state().phase, BreakPhase::Disabled);
+        assert_eq!(next_break(&scheduler), (None, None));
+        assert_eq!(scheduler.time_until_due(None), None);
+        assert!(scheduler.start_break().is_err());
+    }
+
+    #[test]
+    fn working_window_opening_starts_a_fresh_cycle() {
+        let (scheduler, clock) = scheduler();
+        for _ in 0..2 {
+            clock.advance(Duration::from_secs(20 * 60));
+            take_break(&scheduler, &clock);
+        }
+        scheduler.skip_break().unwrap();
+
+        scheduler.settings.lock().unwrap().working_hours = working_hours(false);
+        scheduler.check_working_hours();
+        clock.advance(Duration::from_secs(12 * 60 * 60));
+        scheduler.settings.lock().unwrap().working_hours = working_hours(true);
+        assert!(scheduler.check_working_hours());
+
+        let state = scheduler.get_state();
+        assert_eq!(state.phase, BreakPhase::Scheduled);
+        assert_eq!(state.microbreaks_since_longbreak, 0);
+        assert_eq!(state.consecutive_dodges, 0);
+        assert_eq!(
+            next_break(&scheduler),
+            (Some(BreakType::Microbreak), Some(clock.now_ms() + 20 * MINUTE))
+        );
+        assert!(!scheduler.check_working_hours());
+    }
+
+    #[test]
+    fn breaks_on_screen_and_pauses_outlast_working_hours() {
+        let (scheduler, clock) = scheduler();
+        clock.advance(Duration::from_secs(20 * 60));
+        scheduler.start_break().unwrap();
+
+        scheduler.settings.lock().unwrap().working_hours = working_hours(false);
+        assert!(!scheduler.check_working_hours());
+        assert_eq!(scheduler.get_state().phase, BreakPhase::Active);
+
+        clock.advance(Duration::from_secs(20));
+        scheduler.complete_break().unwrap();
+        scheduler.pause(None).unwrap();
+        assert!(!scheduler.check_working_hours());
+        assert_eq!(scheduler.get_state().phase, BreakPhase::Paused);
+    }
+
+    /// Settings with a daily fixed break at the local time of day of `at`, so tests
+    /// don't depend on the machine's timezone.
+    fn with_fixed_break(name: &str, at: u64) -> BreakSettings {
+        use chrono::TimeZone;
+        let time = chrono::Local.timestamp_millis_opt(at as i64).unwrap().format("%H:%M").to_string();
+        BreakSettings {
+            fixed_breaks: vec![crate::fixed_breaks::FixedBreak {
+                name: name.to_string(),
+                at: format!("daily {}", time),
+                duration_minutes: 30,
+                strict: false,
+            }],
+            ..BreakSettings::default()
+        }
+    }
+
+    fn fixed(name: &str) -> Option<BreakType> {
+        Some(BreakType::Fixed(name.to_string()))
+    }
+
+    #[test]
+    fn fixed_break_merges_with_an_interval_break_just_before_it() {
+        let settings = with_fixed_break("Lunch", START + 23 * MINUTE);
+        let lunch_at = crate::fixed_breaks::next_fixed_break(&settings.fixed_breaks, START).unwrap().1;
+        let (scheduler, _clock) = scheduler_with(settings);
+
+        assert_eq!(next_break(&scheduler), (fixed("Lunch"), Some(lunch_at)));
+        assert_eq!(scheduler.get_state().break_number, 0);
+
+        let (scheduler, _clock) = scheduler_with(with_fixed_break("Lunch", START + 60 * MINUTE));
+        assert_eq!(next_break(&scheduler), (Some(BreakType::Microbreak), Some(START + 20 * MINUTE)));
+    }
+
+    #[test]
+    fn fixed_break_restarts_the_interval_cycle() {
+        let (scheduler, clock) = scheduler_with(with_fixed_break("Lunch", START + 63 * MINUTE));
+        for _ in 0..2 {
+            clock.set(next_break(&scheduler).1.unwrap());
+            take_break(&scheduler, &clock);
+        }
+        assert_eq!(scheduler.get_state().microbreaks_since_longbreak, 2);
+
+        let (break_type, scheduled) = next_break(&scheduler);
+        assert_eq!(break_type, fixed("Lunch"));
+        clock.set(scheduled.unwrap());
+        take_break(&scheduler, &clock);
+
+        assert_eq!(scheduler.get_state().microbreaks_since_longbreak, 0);
+        assert_eq!(
+            next_break(&scheduler),
+            (Some(BreakType::Microbreak), Some(scheduled.unwrap() + 50 * MINUTE))
+        );
+    }
+
+    #[test]
+    fn skipped_fixed_break_does_not_come_back_the_same_day() {
+        let (scheduler, _clock) = scheduler_with(with_fixed_break("Stretch", START + 10 * MINUTE));
+        assert_eq!(next_break(&scheduler).0, fixed("Stretch"));
+
+        scheduler.skip_break().unwrap();
+
+        assert_eq!(next_break(&scheduler), (Some(BreakType::Microbreak), Some(START + 20 * MINUTE)));
+    }
+
+    #[test]
+    fn postponing_into_a_fixed_break_merges_with_it() {
+        let settings = with_fixed_break("Lunch", START + 23 * MINUTE);
+        let lunch_at = crate::fixed_breaks::next_fixed_break(&settings.fixed_breaks, START).unwrap().1;
+        let (scheduler, clock) = scheduler_with(BreakSettings {
+            microbreak_interval_minutes: 10,
+            ..settings
+        });
+        assert_eq!(next_break(&scheduler).0, Some(BreakType::Microbreak));
+
+        clock.set(START + 10 * MINUTE);
+        scheduler.postpone_break(10).unwrap();
+        assert_eq!(next_break(&scheduler), (fixed("Lunch"), Some(lunch_at)));
+
+        // Postponing the fixed break itself does move it
+        clock.set(lunch_at);
+        scheduler.postpone_break(5).unwrap();
+        assert_eq!(next_break(&scheduler), (fixed("Lunch"), Some(lunch_at + 5 * MINUTE)));
+    }
+
+    fn kind(name: &str, interval_minutes: u32, duration_seconds: u32) -> crate::models::BreakKind {
+        crate::models::BreakKind {
+            name: name.to_string(),
+            interval_minutes,
+            duration_seconds,
+            strict: false,
+            presentation: Presentation::Window,
+            message: String::new(),
+        }
+    }
+
+    fn custom(name: &str) -> Option<BreakType> {
+        Some(BreakType::Custom(name.to_string()))
+    }
+
+    #[test]
+    fn break_kinds_run_on_their_own_timers() {
+        let (scheduler, clock) = scheduler_with(BreakSettings {
+            break_kinds: vec![kind("Eye rest", 10, 10)],
+            ..BreakSettings::default()
+        });
+        assert_eq!(next_break(&scheduler), (custom("Eye rest"), Some(START + 10 * MINUTE)));
+
+        clock.set(START + 10 * MINUTE);
+        take_break(&scheduler, &clock);
+        // Too short to stand in for the microbreak, which keeps its own deadline
+        assert_eq!(scheduler.get_state().microbreaks_since_longbreak, 0);
+
+        // Eye rest is due again 10s after the microbreak: the longer microbreak wins and
+        // counts for it
+        assert_eq!(next_break(&scheduler), (Some(BreakType::Microbreak), Some(START + 20 * MINUTE)));
+        clock.set(START + 20 * MINUTE);
+        take_break(&scheduler, &clock);
+        assert_eq!(scheduler.get_state().kind_due_at["Eye rest"], clock.now_ms() + 10 * MINUTE);
+    }
+
+    #[test]
+    fn longest_of_coincident_breaks_wins_and_counts_for_the_others() {
+        let (scheduler, clock) = scheduler_with(BreakSettings {
+            break_kinds: vec![kind("Eye rest", 20, 10), kind("Stretch", 20, 120)],
+            ..BreakSettings::default()
+        });
+        assert_eq!(next_break(&scheduler), (custom("Stretch"), Some(START + 20 * MINUTE)));
+
+        clock.set(START + 20 * MINUTE);
+        take_break(&scheduler, &clock);
+
+        let now = clock.now_ms();
+        let state = scheduler.get_state();
+        assert_eq!(state.kind_due_at["Eye rest"], now + 20 * MINUTE);
+        assert_eq!(state.kind_due_at["Stretch"], now + 20 * MINUTE);
+        // Long enough to stand in for the microbreak, but not for a long break
+        assert_eq!(state.microbreaks_since_longbreak, 1);
+        assert_eq!(scheduler.scheduler_state.lock().unwrap().scheduled_break_time, Some(now + 20 * MINUTE));
+    }
+
+    #[test]
+    fn skipping_or_postponing_a_break_kind_leaves_the_interval_schedule_alone() {
+        let (scheduler, clock) = scheduler_with(BreakSettings {
+            break_kinds: vec![kind("Hydrate", 10, 30)],
+            ..BreakSettings::default()
+        });
+
+        clock.set(START + 10 * MINUTE);
+        scheduler.start_break().unwrap();
+        scheduler.postpone_break(5).unwrap();
+        assert_eq!(next_break(&scheduler), (custom("Hydrate"), Some(START + 15 * MINUTE)));
+
+        clock.set(START + 15 * MINUTE);
+        scheduler.skip_break().unwrap();
+        assert_eq!(scheduler.get_state().kind_due_at["Hydrate"], START + 25 * MINUTE);
+        assert_eq!(next_break(&scheduler), (Some(BreakType::Microbreak), Some(START + 20 * MINUTE)));
+        assert_eq!(scheduler.get_state().skip_count, 1);
+    }
+
+    #[test]
+    fn strict_break_kind_cannot_be_skipped() {
+        let mut stretch = kind("Stretch", 10, 60);
+        stretch.strict = true;
+        let (scheduler, clock) = scheduler_with(BreakSettings {
+            break_kinds: vec![stretch],
+            ..BreakSettings::default()
+        });
+
+        clock.set(START + 10 * MINUTE);
+        scheduler.start_break().unwrap();
+        assert!(scheduler.get_state().strict);
+        assert_eq!(scheduler.skip_break(), Err(BreakError::Strict));
+    }
+
+    fn pomodoro(mode: PomodoroMode) -> BreakSettings {
+        BreakSettings {
+            // Out of the pomodoros' way
+            microbreak_interval_minutes: 60,
+            pomodoro: crate::models::PomodoroSettings {
+                mode,
+                long_break_every: 2,
+                ..crate::models::PomodoroSettings::default()
+            },
+            ..BreakSettings::default()
+        }
+    }
+
+    #[test]
+    fn pomodoros_end_in_a_break_and_every_nth_break_is_long() {
+        let (mut scheduler, clock) = scheduler_with(pomodoro(PomodoroMode::Alongside));
+        let dir = TempDir::new("pomodoro");
+        let history = crate::history::history_path(&dir);
+        scheduler.set_history_path(history.clone());
+
+        scheduler.start_pomodoro(Some("  Write report ".to_string())).unwrap();
+        assert_eq!(scheduler.start_pomodoro(None), Err(BreakError::PomodoroRunning));
+        assert_eq!(next_break(&scheduler), (Some(BreakType::PomodoroBreak), Some(START + 25 * MINUTE)));
+
+        clock.set(START + 25 * MINUTE);
+        take_break(&scheduler, &clock);
+        assert_eq!(scheduler.get_state().pomodoro, None);
+        assert_eq!(scheduler.get_state().pomodoros_since_long_break, 1);
+        // As long as a long break, so the interval cycle starts over
+        let now = clock.now_ms();
+        assert_eq!(scheduler.scheduler_state.lock().unwrap().scheduled_break_time, Some(now + 60 * MINUTE));
+
+        scheduler.start_pomodoro(None).unwrap();
+        assert_eq!(next_break(&scheduler).0, Some(BreakType::PomodoroLongBreak));
+        clock.advance(Duration::from_secs(25 * 60));
+        take_break(&scheduler, &clock);
+        assert_eq!(scheduler.get_state().pomodoros_since_long_break, 0);
+
+        let records: Vec<_> = crate::history::load(&history)
+            .into_iter()
+            .filter(|record| record.event == HistoryEvent::PomodoroStarted || record.event == HistoryEvent::PomodoroCompleted)
+            .collect();
+        let events: Vec<_> = records.iter().map(|record| (record.event, record.label.as_deref())).collect();
+        assert_eq!(
+            events,
+            vec![
+                (HistoryEvent::PomodoroStarted, Some("Write report")),
+                (HistoryEvent::PomodoroCompleted, Some("Write report")),
+                (HistoryEvent::PomodoroStarted, None),
+                (HistoryEvent::PomodoroCompleted, None),
+            ]
+        );
+        assert_eq!(records[1].duration_ms, Some(25 * MINUTE));
+        assert_eq!(scheduler.recent_labels(), vec!["Write report".to_string()]);
+    }
+
+    #[test]
+    fn pomodoros_can_replace_the_interval_breaks() {
+        let (scheduler, clock) = scheduler_with(pomodoro(PomodoroMode::Instead));
+        assert_eq!(next_break(&scheduler), (None, None));
+        assert_eq!(scheduler.time_until_due(None), None);
+
+        scheduler.start_pomodoro(None).unwrap();
+        assert_eq!(next_break(&scheduler), (Some(BreakType::PomodoroBreak), Some(START + 25 * MINUTE)));
+
+        // Postponing the break means working on a little longer
+        clock.set(START + 25 * MINUTE);
+        scheduler.postpone_break(5).unwrap();
+        assert_eq!(next_break(&scheduler), (Some(BreakType::PomodoroBreak), Some(START + 30 * MINUTE)));
+
+        clock.set(START + 30 * MINUTE);
+        take_break(&scheduler, &clock);
+        assert_eq!(next_break(&scheduler), (None, None));
+    }
+
+    #[test]
+    fn interrupted_or_voided_pomodoros_do_not_count() {
+        let (scheduler, clock) = scheduler_with(pomodoro(PomodoroMode::Alongside));
+        assert_eq!(scheduler.interrupt_pomodoro(), Err(BreakError::NoPomodoro));
+
+        scheduler.start_pomodoro(None).unwrap();
+        clock.advance(Duration::from_secs(10 * 60));
+        scheduler.interrupt_pomodoro().unwrap();
+        assert_eq!(next_break(&scheduler), (Some(BreakType::Microbreak), Some(START + 60 * MINUTE)));
+
+        scheduler.start_pomodoro(None).unwrap();
+        scheduler.void_pomodoro().unwrap();
+        assert_eq!(scheduler.get_state().pomodoros_since_long_break, 0);
+
+        let (off, _clock) = scheduler_with(BreakSettings::default());
+        assert_eq!(off.start_pomodoro(None), Err(BreakError::PomodoroOff));
     }
 }
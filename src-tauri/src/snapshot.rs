@@ -0,0 +1,87 @@
+use crate::lifecycle::BreakPhase;
+use crate::models::{BreakSettings, BreakState, BreakType};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const SNAPSHOT_FILE: &str = "scheduler.json";
+
+/// Scheduler progress written to disk on every transition (and periodically as a
+/// heartbeat), so restarting the app can't be used to dodge a break.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulerSnapshot {
+    /// Last time the app was known to be running (Unix ms).
+    pub saved_at: u64,
+    pub state: BreakState,
+    pub scheduled_break_time: Option<u64>,
+    pub current_break_type: Option<BreakType>,
+}
+
+/// What to do with a snapshot restored at startup.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResumePlan {
+    /// The saved break time is still ahead: keep it.
+    Keep(u64),
+    /// The break came due (or was on screen) while the app was down: show it now.
+    DueNow,
+    /// Nothing was scheduled: schedule normally, keeping the cycle position.
+    Reschedule,
+    /// The app was down for at least a long break: count that as a long break
+    /// and start a fresh cycle.
+    FreshCycle,
+}
+
+pub fn snapshot_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(SNAPSHOT_FILE)
+}
+
+pub fn load_snapshot(path: &Path) -> Option<SchedulerSnapshot> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            println!("Warning: could not read scheduler snapshot {:?}: {}", path, e);
+            return None;
+        }
+    };
+    match serde_json::from_str(&contents) {
+        Ok(snapshot) => Some(snapshot),
+        Err(e) => {
+            println!("Warning: ignoring corrupt scheduler snapshot {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+pub fn save_snapshot(path: &Path, snapshot: &SchedulerSnapshot) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(snapshot)
+        .map_err(|e| format!("Failed to serialize scheduler snapshot: {}", e))?;
+    crate::config::write_atomic(path, json.as_bytes())
+}
+
+/// Decide how to resume from `snapshot` at `now` (Unix ms).
+///
+/// Downtime is measured from the last heartbeat. Being away for at least a long
+/// break resets the cycle; otherwise a break that came due while the app was
+/// closed is shown straight away rather than skipped. When only active time counts,
+/// the downtime isn't active time, so the deadline moves out by that much.
+pub fn resume_plan(snapshot: &SchedulerSnapshot, now: u64, settings: &BreakSettings) -> ResumePlan {
+    let downtime = now.saturating_sub(snapshot.saved_at);
+    let longbreak_ms = settings.longbreak_duration_minutes as u64 * 60 * 1000;
+
+    if downtime >= longbreak_ms {
+        return ResumePlan::FreshCycle;
+    }
+
+    let break_was_on_screen = snapshot.state.phase == BreakPhase::Active;
+    let scheduled_break_time = match snapshot.scheduled_break_time {
+        Some(scheduled) if settings.count_active_time_only => Some(scheduled + downtime),
+        scheduled => scheduled,
+    };
+    match scheduled_break_time {
+        Some(scheduled) if scheduled > now && !break_was_on_screen => ResumePlan::Keep(scheduled),
+        Some(_) => ResumePlan::DueNow,
+        None if break_was_on_screen => ResumePlan::DueNow,
+        None => ResumePlan::Reschedule,
+    }
+}
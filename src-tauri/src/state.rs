@@ -1,17 +1,46 @@
 use crate::models::BreakSettings;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 pub struct AppState {
     pub settings: Arc<Mutex<BreakSettings>>,
     pub scheduler: Arc<Mutex<crate::scheduler::BreakScheduler>>,
+    pub settings_path: PathBuf,
+    /// Named settings to switch between; the active one follows settings changes.
+    pub profiles: Arc<Mutex<crate::profiles::Profiles>>,
+    pub profiles_path: PathBuf,
+    /// Append-only log of pomodoros and breaks.
+    pub history_path: PathBuf,
+    /// What the loader had to fix when reading the settings file at startup.
+    pub settings_notes: Vec<String>,
 }
 
-impl Default for AppState {
-    fn default() -> Self {
-        let settings = Arc::new(Mutex::new(BreakSettings::default()));
+impl AppState {
+    pub fn load(config_dir: &Path, data_dir: &Path) -> Self {
+        let settings_path = crate::config::settings_path(config_dir);
+        let loaded = crate::config::load_settings(&settings_path);
+        let settings = Arc::new(Mutex::new(loaded.settings));
+        let profiles_path = crate::profiles::profiles_path(config_dir);
+        let profiles = Arc::new(Mutex::new(crate::profiles::load(&profiles_path)));
+
+        let history_path = crate::history::history_path(data_dir);
+        let mut scheduler = crate::scheduler::BreakScheduler::new(settings.clone());
+        scheduler.set_history_path(history_path.clone());
+        let history = crate::history::load(&history_path);
+        if crate::history::last_settings(&history) != Some(&*settings.lock().unwrap()) {
+            scheduler.record_settings();
+        }
+        scheduler.set_recent_labels(crate::history::recent_labels(&history, crate::scheduler::RECENT_POMODORO_LABELS));
+        scheduler.restore(crate::snapshot::snapshot_path(data_dir));
+
         Self {
-            settings: settings.clone(),
-            scheduler: Arc::new(Mutex::new(crate::scheduler::BreakScheduler::new(settings))),
+            settings,
+            scheduler: Arc::new(Mutex::new(scheduler)),
+            settings_path,
+            profiles,
+            profiles_path,
+            history_path,
+            settings_notes: loaded.notes,
         }
     }
 }
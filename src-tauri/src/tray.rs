@@ -1,12 +1,74 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use tauri_plugin_opener::OpenerExt;
 use tauri::{
-    menu::{Menu, MenuItem},
+    image::Image,
+    menu::{CheckMenuItem, Menu, MenuItem, Submenu},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent, TrayIcon},
     Manager,
     WebviewUrl,
     WebviewWindowBuilder,
     AppHandle,
+    Wry,
 };
-use std::time::{SystemTime, UNIX_EPOCH};
+
+const TRAY_ID: &str = "main";
+
+/// Menu ids of the postpone choices are `postpone:<minutes>`.
+const POSTPONE_PREFIX: &str = "postpone:";
+
+/// Menu ids of the pause choices are `pause:<minutes>`, `pause:end_of_day`, `pause:tomorrow`
+/// or `pause:indefinitely`.
+const PAUSE_PREFIX: &str = "pause:";
+
+/// Menu ids starting a pomodoro for one of the recent task labels are
+/// `pomodoro:start:<label>`.
+const POMODORO_LABEL_PREFIX: &str = "pomodoro:start:";
+
+/// Menu ids of the export choices are `export:<csv|jsonl>:<days>`, exporting the last
+/// `days` days including today.
+const EXPORT_PREFIX: &str = "export:";
+
+/// Menu ids switching to a settings profile are `profile:<name>`.
+const PROFILE_PREFIX: &str = "profile:";
+
+/// Whether the tray currently shows the warning icon.
+static WARNING_SHOWN: AtomicBool = AtomicBool::new(false);
+
+/// The app icon tinted amber, shown while a break is announced.
+static WARNING_ICON: OnceLock<Image<'static>> = OnceLock::new();
+
+/// The task label of a `pomodoro:start:<label>` menu id.
+fn pomodoro_label(id: &str) -> Option<String> {
+    let label = id.strip_prefix(POMODORO_LABEL_PREFIX)?.trim();
+    (!label.is_empty()).then(|| label.to_string())
+}
+
+fn start_pomodoro(app: &AppHandle, label: Option<String>) {
+    println!("Tray: start pomodoro {:?}", label);
+    let app_state = app.state::<crate::state::AppState>();
+    let result = app_state.scheduler.lock().unwrap().start_pomodoro(label);
+    match result {
+        Ok(()) => refresh_menu(app),
+        Err(e) => println!("Tray: cannot start pomodoro: {}", e),
+    }
+}
+
+/// Local time of day, with the weekday if it isn't today.
+fn format_local_time(ms: u64, now_ms: u64) -> String {
+    use chrono::{Local, TimeZone};
+    let (Some(time), Some(now)) = (
+        Local.timestamp_millis_opt(ms as i64).single(),
+        Local.timestamp_millis_opt(now_ms as i64).single(),
+    ) else {
+        return "later".to_string();
+    };
+    if time.date_naive() == now.date_naive() {
+        time.format("%H:%M").to_string()
+    } else {
+        time.format("%a %H:%M").to_string()
+    }
+}
 
 fn format_time_left(ms: u64) -> String {
     let total_seconds = ms / 1000;
@@ -24,43 +86,211 @@ fn format_time_left(ms: u64) -> String {
     }
 }
 
+fn break_type_label(break_type: Option<&crate::models::BreakType>) -> &str {
+    match break_type {
+        Some(crate::models::BreakType::Microbreak) => "Microbreak",
+        Some(crate::models::BreakType::Longbreak) => "Long break",
+        Some(crate::models::BreakType::Fixed(name) | crate::models::BreakType::Custom(name)) => name.as_str(),
+        Some(crate::models::BreakType::PomodoroBreak) => "Pomodoro break",
+        Some(crate::models::BreakType::PomodoroLongBreak) => "Long pomodoro break",
+        None => "Break",
+    }
+}
+
 fn get_tray_tooltip(app: &AppHandle) -> String {
     let app_state = app.state::<crate::state::AppState>();
+    let working_hours = app_state.settings.lock().unwrap().working_hours.clone();
     let scheduler = app_state.scheduler.lock().unwrap();
     let scheduler_state = scheduler.get_scheduler_state();
+    let state = scheduler.get_state();
+
+    if state.phase == crate::lifecycle::BreakPhase::Disabled {
+        let now = scheduler.now_ms();
+        return match working_hours.next_window_start(now) {
+            Some(start) => format!("Off hours until {}", format_local_time(start, now)),
+            None => "Off hours".to_string(),
+        };
+    }
+
+    if state.phase == crate::lifecycle::BreakPhase::Paused {
+        return match state.paused_until {
+            Some(until) => format!("Paused until {}", format_local_time(until, scheduler.now_ms())),
+            None => "Paused".to_string(),
+        };
+    }
+
+    if let (crate::lifecycle::BreakPhase::Warning, Some(ends_at)) = (state.phase, state.warning_ends_at) {
+        let time_left = ends_at.saturating_sub(scheduler.now_ms());
+        let break_type = break_type_label(scheduler_state.current_break_type.as_ref());
+        return format!("{} starts in {}", break_type, format_time_left(time_left));
+    }
+
+    if let Some(ref session) = state.pomodoro {
+        let task = session.label.as_deref().map(|label| format!(" on {}", label)).unwrap_or_default();
+        let time_left = session.ends_at.saturating_sub(scheduler.now_ms());
+        return format!("Pomodoro{}: {} left", task, format_time_left(time_left));
+    }
 
     if let Some(scheduled_time) = scheduler_state.scheduled_break_time {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+        let now = scheduler.now_ms();
 
         let time_left = scheduled_time.saturating_sub(now);
 
-        let break_type = match scheduler_state.current_break_type {
-            Some(crate::models::BreakType::Microbreak) => "Microbreak",
-            Some(crate::models::BreakType::Longbreak) => "Long break",
-            None => "Break",
-        };
+        let break_type = break_type_label(scheduler_state.current_break_type.as_ref());
 
-        format!("Next: {} in {}", break_type, format_time_left(time_left))
+        if scheduler_state.active_time_only {
+            format!("Next: {} after {} of activity", break_type, format_time_left(time_left))
+        } else {
+            format!("Next: {} in {}", break_type, format_time_left(time_left))
+        }
     } else {
         "No break scheduled".to_string()
     }
 }
 
-pub fn setup_tray(app: &tauri::App) -> Result<TrayIcon, Box<dyn std::error::Error>> {
+fn build_menu<M: Manager<Wry>>(app: &M) -> tauri::Result<Menu<Wry>> {
     let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
     let preferences_item = MenuItem::with_id(app, "preferences", "Preferences...", true, None::<&str>)?;
     let test_break_item = MenuItem::with_id(app, "test_break", "Test Break", true, None::<&str>)?;
 
-    let menu = Menu::with_items(app, &[&test_break_item, &preferences_item, &quit_item])?;
+    let durations = app.state::<crate::state::AppState>().settings.lock().unwrap().postpone_durations_minutes.clone();
+    let postpone_items = durations
+        .iter()
+        .map(|minutes| {
+            let label = format!("{} minutes", minutes);
+            MenuItem::with_id(app, format!("{}{}", POSTPONE_PREFIX, minutes), label, true, None::<&str>)
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+    let postpone_refs: Vec<&dyn tauri::menu::IsMenuItem<Wry>> =
+        postpone_items.iter().map(|item| item as &dyn tauri::menu::IsMenuItem<Wry>).collect();
+    let postpone_menu = Submenu::with_items(app, "Postpone Next Break", true, &postpone_refs)?;
+
+    let working_hours = app.state::<crate::state::AppState>().settings.lock().unwrap().working_hours.enabled;
+    let pause_menu = Submenu::with_items(
+        app,
+        "Pause Breaks",
+        true,
+        &[
+            &MenuItem::with_id(app, "pause:15", "15 minutes", true, None::<&str>)?,
+            &MenuItem::with_id(app, "pause:60", "1 hour", true, None::<&str>)?,
+            &MenuItem::with_id(app, "pause:end_of_day", "Until end of working hours", working_hours, None::<&str>)?,
+            &MenuItem::with_id(app, "pause:tomorrow", "Until tomorrow", true, None::<&str>)?,
+            &MenuItem::with_id(app, "pause:indefinitely", "Until I resume", true, None::<&str>)?,
+        ],
+    )?;
+    let resume_item = MenuItem::with_id(app, "resume", "Resume Breaks", true, None::<&str>)?;
+
+    let app_state = app.state::<crate::state::AppState>();
+    let pomodoro_mode = app_state.settings.lock().unwrap().pomodoro.mode;
+    let recent_labels = app_state.scheduler.lock().unwrap().recent_labels();
+    let mut pomodoro_items = vec![MenuItem::with_id(app, "pomodoro:start", "Start Pomodoro", true, None::<&str>)?];
+    for label in recent_labels {
+        let text = format!("Start: {}", label);
+        pomodoro_items.push(MenuItem::with_id(app, format!("{}{}", POMODORO_LABEL_PREFIX, label), text, true, None::<&str>)?);
+    }
+    pomodoro_items.push(MenuItem::with_id(app, "pomodoro:interrupt", "Interrupt Pomodoro", true, None::<&str>)?);
+    pomodoro_items.push(MenuItem::with_id(app, "pomodoro:void", "Void Pomodoro", true, None::<&str>)?);
+    let pomodoro_refs: Vec<&dyn tauri::menu::IsMenuItem<Wry>> =
+        pomodoro_items.iter().map(|item| item as &dyn tauri::menu::IsMenuItem<Wry>).collect();
+    let pomodoro_enabled = pomodoro_mode != crate::models::PomodoroMode::Off;
+    let pomodoro_menu = Submenu::with_items(app, "Pomodoro", pomodoro_enabled, &pomodoro_refs)?;
+
+    let profiles = app_state.profiles.lock().unwrap().list();
+    let profile_items = profiles
+        .names
+        .iter()
+        .map(|name| {
+            let active = profiles.active.as_ref() == Some(name);
+            CheckMenuItem::with_id(app, format!("{}{}", PROFILE_PREFIX, name), name, true, active, None::<&str>)
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+    let profile_refs: Vec<&dyn tauri::menu::IsMenuItem<Wry>> =
+        profile_items.iter().map(|item| item as &dyn tauri::menu::IsMenuItem<Wry>).collect();
+    // Profiles are made in Preferences
+    let profile_menu = Submenu::with_items(app, "Profile", !profile_items.is_empty(), &profile_refs)?;
+
+    let export_menu = Submenu::with_items(
+        app,
+        "Export History",
+        true,
+        &[
+            &MenuItem::with_id(app, "export:csv:7", "Last 7 days (CSV)", true, None::<&str>)?,
+            &MenuItem::with_id(app, "export:csv:30", "Last 30 days (CSV)", true, None::<&str>)?,
+            &MenuItem::with_id(app, "export:jsonl:30", "Last 30 days (JSON lines)", true, None::<&str>)?,
+        ],
+    )?;
+
+    Menu::with_items(
+        app,
+        &[
+            &test_break_item,
+            &pomodoro_menu,
+            &postpone_menu,
+            &pause_menu,
+            &resume_item,
+            &profile_menu,
+            &export_menu,
+            &preferences_item,
+            &quit_item,
+        ],
+    )
+}
+
+/// Blend every pixel halfway towards amber, keeping transparency.
+fn tint_amber(icon: &Image<'_>) -> Image<'static> {
+    const AMBER: [u8; 3] = [255, 176, 0];
+    let mut rgba = icon.rgba().to_vec();
+    for pixel in rgba.chunks_exact_mut(4) {
+        for (channel, amber) in pixel.iter_mut().zip(AMBER) {
+            *channel = ((*channel as u16 + amber as u16) / 2) as u8;
+        }
+    }
+    Image::new_owned(rgba, icon.width(), icon.height())
+}
+
+/// Switch to the warning icon while a break is announced, and back afterwards.
+pub fn show_phase(app: &AppHandle, phase: crate::lifecycle::BreakPhase) {
+    let warning = phase == crate::lifecycle::BreakPhase::Warning;
+    if WARNING_SHOWN.swap(warning, Ordering::Relaxed) == warning {
+        return;
+    }
+    let (Some(tray), Some(icon)) = (app.tray_by_id(TRAY_ID), app.default_window_icon()) else {
+        return;
+    };
+    let icon = if warning {
+        WARNING_ICON.get_or_init(|| tint_amber(icon)).clone()
+    } else {
+        icon.clone()
+    };
+    if let Err(e) = tray.set_icon(Some(icon)) {
+        println!("Tray: failed to update icon: {}", e);
+    }
+}
+
+/// Rebuild the menu after something it shows (postpone choices, working hours, recent
+/// pomodoro labels) changed.
+pub fn refresh_menu(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    match build_menu(app) {
+        Ok(menu) => {
+            if let Err(e) = tray.set_menu(Some(menu)) {
+                println!("Tray: failed to update menu: {}", e);
+            }
+        }
+        Err(e) => println!("Tray: failed to build menu: {}", e),
+    }
+}
+
+pub fn setup_tray(app: &tauri::App) -> Result<TrayIcon, Box<dyn std::error::Error>> {
+    let menu = build_menu(app)?;
 
-    let tray = TrayIconBuilder::new()
+    let tray = TrayIconBuilder::with_id(TRAY_ID)
         .icon(app.default_window_icon().unwrap().clone())
         .menu(&menu)
         .show_menu_on_left_click(false)
-        .tooltip(&get_tray_tooltip(&app.handle()))
+        .tooltip(get_tray_tooltip(app.handle()))
         .on_menu_event(move |app, event| match event.id().as_ref() {
             "quit" => {
                 app.exit(0);
@@ -93,42 +323,111 @@ pub fn setup_tray(app: &tauri::App) -> Result<TrayIcon, Box<dyn std::error::Erro
             "test_break" => {
                 println!("Tray: test_break menu item clicked");
 
-                // Get settings to check fullscreen preference
+                // Start the upcoming break now, through the scheduler so its lifecycle is tracked
+                let app_state = app.state::<crate::state::AppState>();
+                let scheduler = app_state.scheduler.lock().unwrap();
+                if let Err(e) = scheduler.start_break() {
+                    println!("Tray: cannot start break: {}", e);
+                }
+            }
+            id if id.starts_with(PAUSE_PREFIX) => {
                 let app_state = app.state::<crate::state::AppState>();
-                let settings = app_state.settings.lock().unwrap();
-                let fullscreen = settings.fullscreen_breaks;
-                drop(settings); // Release the lock
+                let working_hours = app_state.settings.lock().unwrap().working_hours.clone();
+                let scheduler = app_state.scheduler.lock().unwrap();
+                let now = scheduler.now_ms();
+                let until = match &id[PAUSE_PREFIX.len()..] {
+                    "end_of_day" => match working_hours.window_end(now) {
+                        Some(end) => Some(end),
+                        None => {
+                            println!("Tray: not within working hours, nothing to pause");
+                            return;
+                        }
+                    },
+                    // The next working day's start, or midnight without a schedule
+                    "tomorrow" => Some(
+                        working_hours
+                            .next_day_start(now)
+                            .unwrap_or_else(|| crate::clock::next_local_midnight(now)),
+                    ),
+                    "indefinitely" => None,
+                    minutes => match minutes.parse::<u64>() {
+                        Ok(minutes) => Some(now + minutes * 60 * 1000),
+                        Err(_) => return,
+                    },
+                };
+                println!("Tray: pause until {:?}", until);
+                if let Err(e) = scheduler.pause(until) {
+                    println!("Tray: cannot pause breaks: {}", e);
+                }
+            }
+            "pomodoro:start" => start_pomodoro(app, None),
+            id if id.starts_with(POMODORO_LABEL_PREFIX) => start_pomodoro(app, pomodoro_label(id)),
+            "pomodoro:interrupt" => {
+                let app_state = app.state::<crate::state::AppState>();
+                let scheduler = app_state.scheduler.lock().unwrap();
+                if let Err(e) = scheduler.interrupt_pomodoro() {
+                    println!("Tray: cannot interrupt pomodoro: {}", e);
+                }
+            }
+            "pomodoro:void" => {
+                let app_state = app.state::<crate::state::AppState>();
+                let scheduler = app_state.scheduler.lock().unwrap();
+                if let Err(e) = scheduler.void_pomodoro() {
+                    println!("Tray: cannot void pomodoro: {}", e);
+                }
+            }
+            id if id.starts_with(PROFILE_PREFIX) => {
+                let name = &id[PROFILE_PREFIX.len()..];
+                println!("Tray: switch to profile {}", name);
+                if let Err(e) = crate::commands::activate(app, name) {
+                    println!("Tray: cannot switch profile: {}", e);
+                    // Undo the check mark toggled by the click
+                    refresh_menu(app);
+                }
+            }
+            id if id.starts_with(EXPORT_PREFIX) => {
+                let Some((format, days)) = id[EXPORT_PREFIX.len()..].split_once(':') else {
+                    return;
+                };
+                let format = match format {
+                    "csv" => crate::export::ExportFormat::Csv,
+                    "jsonl" => crate::export::ExportFormat::JsonLines,
+                    _ => return,
+                };
+                let Ok(days) = days.parse::<u64>() else {
+                    return;
+                };
+                let now = app.state::<crate::state::AppState>().scheduler.lock().unwrap().now_ms();
+                let Some(today) = crate::clock::local_date(&chrono::Local, now) else {
+                    return;
+                };
+                let from = today - chrono::Days::new(days.saturating_sub(1));
+                match crate::export::export_history(app, from, today, format) {
+                    Ok(path) => {
+                        if let Err(e) = app.opener().reveal_item_in_dir(&path) {
+                            println!("Tray: cannot show {:?}: {}", path, e);
+                        }
+                    }
+                    Err(e) => println!("Tray: cannot export history: {}", e),
+                }
+            }
+            "resume" => {
+                let app_state = app.state::<crate::state::AppState>();
+                let scheduler = app_state.scheduler.lock().unwrap();
+                if let Err(e) = scheduler.resume() {
+                    println!("Tray: cannot resume breaks: {}", e);
+                }
+            }
+            id if id.starts_with(POSTPONE_PREFIX) => {
+                let Ok(minutes) = id[POSTPONE_PREFIX.len()..].parse::<u32>() else {
+                    return;
+                };
+                println!("Tray: postpone by {} minutes", minutes);
 
-                // Check if break window already exists
-                if let Some(window) = app.get_webview_window("break") {
-                    let _ = window.show();
-                    let _ = window.set_focus();
-                } else {
-                    // Create new break window with settings from preferences
-                    // Both fullscreen and windowed modes are frameless (overlay-like)
-                    let builder = WebviewWindowBuilder::new(
-                        app,
-                        "break",
-                        WebviewUrl::App("/break".into())
-                    )
-                    .title("Restie - Break Time")
-                    .decorations(false)
-                    .skip_taskbar(true);
-
-                    let builder = if fullscreen {
-                        // Use maximized instead of native fullscreen for frameless look
-                        // Don't use always_on_top so menu bar remains accessible
-                        builder
-                            .maximized(true)
-                            .resizable(false)
-                    } else {
-                        builder
-                            .inner_size(800.0, 600.0)
-                            .center()
-                            .always_on_top(true)
-                    };
-
-                    let _ = builder.build();
+                let app_state = app.state::<crate::state::AppState>();
+                let scheduler = app_state.scheduler.lock().unwrap();
+                if let Err(e) = scheduler.postpone_break(minutes) {
+                    println!("Tray: cannot postpone break: {}", e);
                 }
             }
             _ => (),
@@ -138,7 +437,7 @@ pub fn setup_tray(app: &tauri::App) -> Result<TrayIcon, Box<dyn std::error::Erro
                 TrayIconEvent::Enter { .. } => {
                     // User is hovering! Compute fresh tooltip
                     let app = tray.app_handle();
-                    let tooltip = get_tray_tooltip(&app);
+                    let tooltip = get_tray_tooltip(app);
                     let _ = tray.set_tooltip(Some(&tooltip));
                     println!("Tooltip updated on hover: {}", tooltip);
                 }
@@ -161,3 +460,16 @@ pub fn setup_tray(app: &tauri::App) -> Result<TrayIcon, Box<dyn std::error::Erro
 
     Ok(tray)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pomodoro_menu_ids_carry_their_label() {
+        assert_eq!(pomodoro_label("pomodoro:start"), None);
+        assert_eq!(pomodoro_label("pomodoro:start:"), None);
+        assert_eq!(pomodoro_label("pomodoro:start:Review: part 2"), Some("Review: part 2".to_string()));
+        assert_eq!(pomodoro_label("pomodoro:interrupt"), None);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::clock::{local_date, local_instant, parse_time_of_day};
+use chrono::{Datelike, Days, Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+
+/// A span of local wall-clock time within one day, as `"HH:MM"`. `end` may be
+/// `"24:00"` for the end of the day; ranges can't wrap past midnight.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeRange {
+    pub start: String,
+    pub end: String,
+}
+
+/// Weekly schedule of when breaks run. Times are local, resolved per day, so a
+/// 09:00 start stays at 09:00 across DST changes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkingHours {
+    pub enabled: bool,
+    /// Seven entries, Monday first; a day without ranges is a day off.
+    pub days: Vec<Vec<TimeRange>>,
+}
+
+impl Default for WorkingHours {
+    fn default() -> Self {
+        let office = vec![TimeRange {
+            start: "09:00".to_string(),
+            end: "17:00".to_string(),
+        }];
+        Self {
+            enabled: false,
+            days: vec![office.clone(), office.clone(), office.clone(), office.clone(), office, vec![], vec![]],
+        }
+    }
+}
+
+impl WorkingHours {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.days.len() != 7 {
+            return Err("Working hours need an entry for each day of the week".to_string());
+        }
+        for ranges in &self.days {
+            let mut previous_end = 0;
+            for range in ranges {
+                let (Some(start), Some(end)) = (parse_time_of_day(&range.start), parse_time_of_day(&range.end)) else {
+                    return Err(format!("Invalid working hours {}-{}: use HH:MM", range.start, range.end));
+                };
+                if start >= end {
+                    return Err(format!("Working hours {}-{} must end after they start", range.start, range.end));
+                }
+                if start < previous_end {
+                    return Err("Working hours within a day must be in order and not overlap".to_string());
+                }
+                previous_end = end;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_working_time(&self, now_ms: u64) -> bool {
+        self.is_working_time_in(&Local, now_ms)
+    }
+
+    /// End of the working window `now_ms` falls in, if any.
+    pub fn window_end(&self, now_ms: u64) -> Option<u64> {
+        self.window_end_in(&Local, now_ms)
+    }
+
+    /// Start of the next working window after `now_ms`.
+    pub fn next_window_start(&self, now_ms: u64) -> Option<u64> {
+        self.next_window_start_in(&Local, now_ms)
+    }
+
+    /// Start of the first working window on a later day than `now_ms`.
+    pub fn next_day_start(&self, now_ms: u64) -> Option<u64> {
+        self.next_day_start_in(&Local, now_ms)
+    }
+
+    fn is_working_time_in<Tz: TimeZone>(&self, tz: &Tz, now_ms: u64) -> bool {
+        !self.enabled || self.window_end_in(tz, now_ms).is_some()
+    }
+
+    fn window_end_in<Tz: TimeZone>(&self, tz: &Tz, now_ms: u64) -> Option<u64> {
+        if !self.enabled {
+            return None;
+        }
+        let today = local_date(tz, now_ms)?;
+        self.windows(tz, today)
+            .find(|&(start, end)| start <= now_ms && now_ms < end)
+            .map(|(_, end)| end)
+    }
+
+    fn next_window_start_in<Tz: TimeZone>(&self, tz: &Tz, now_ms: u64) -> Option<u64> {
+        if !self.enabled {
+            return None;
+        }
+        let today = local_date(tz, now_ms)?;
+        self.windows(tz, today)
+            .map(|(start, _)| start)
+            .find(|&start| start > now_ms)
+            .or_else(|| self.next_day_start_in(tz, now_ms))
+    }
+
+    fn next_day_start_in<Tz: TimeZone>(&self, tz: &Tz, now_ms: u64) -> Option<u64> {
+        if !self.enabled {
+            return None;
+        }
+        let today = local_date(tz, now_ms)?;
+        (1..=7)
+            .filter_map(|offset| today.checked_add_days(Days::new(offset)))
+            .find_map(|date| self.windows(tz, date).next())
+            .map(|(start, _)| start)
+    }
+
+    /// The working windows on `date` as instants.
+    fn windows<'a, Tz: TimeZone>(&'a self, tz: &'a Tz, date: NaiveDate) -> impl Iterator<Item = (u64, u64)> + 'a {
+        let weekday = date.weekday().num_days_from_monday() as usize;
+        self.days.get(weekday).into_iter().flatten().filter_map(move |range| {
+            let start = local_instant(tz, date, parse_time_of_day(&range.start)?)?;
+            let end = local_instant(tz, date, parse_time_of_day(&range.end)?)?;
+            Some((start, end))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use chrono_tz::Europe::Berlin;
+
+    fn at<Tz: TimeZone>(tz: &Tz, date: (i32, u32, u32), time: (u32, u32)) -> u64 {
+        tz.with_ymd_and_hms(date.0, date.1, date.2, time.0, time.1, 0)
+            .earliest()
+            .unwrap()
+            .timestamp_millis() as u64
+    }
+
+    fn office_hours() -> WorkingHours {
+        WorkingHours {
+            enabled: true,
+            ..WorkingHours::default()
+        }
+    }
+
+    #[test]
+    fn breaks_run_only_inside_working_windows() {
+        let hours = office_hours();
+        // 2024-06-03 is a Monday
+        assert!(!hours.is_working_time_in(&Utc, at(&Utc, (2024, 6, 3), (8, 59))));
+        assert!(hours.is_working_time_in(&Utc, at(&Utc, (2024, 6, 3), (9, 0))));
+        assert!(!hours.is_working_time_in(&Utc, at(&Utc, (2024, 6, 3), (17, 0))));
+        assert!(!hours.is_working_time_in(&Utc, at(&Utc, (2024, 6, 8), (12, 0))));
+
+        assert_eq!(
+            hours.window_end_in(&Utc, at(&Utc, (2024, 6, 3), (12, 0))),
+            Some(at(&Utc, (2024, 6, 3), (17, 0)))
+        );
+    }
+
+    #[test]
+    fn next_day_start_skips_days_off() {
+        let mut hours = office_hours();
+        // Friday afternoon -> Monday morning
+        assert_eq!(
+            hours.next_day_start_in(&Utc, at(&Utc, (2024, 6, 7), (16, 0))),
+            Some(at(&Utc, (2024, 6, 10), (9, 0)))
+        );
+
+        // A split day: the afternoon window still comes today
+        hours.days[4] = vec![
+            TimeRange { start: "08:00".to_string(), end: "12:00".to_string() },
+            TimeRange { start: "13:00".to_string(), end: "17:00".to_string() },
+        ];
+        assert_eq!(
+            hours.next_window_start_in(&Utc, at(&Utc, (2024, 6, 7), (12, 30))),
+            Some(at(&Utc, (2024, 6, 7), (13, 0)))
+        );
+    }
+
+    #[test]
+    fn local_times_hold_across_dst_changes() {
+        let hours = office_hours();
+        // Berlin springs forward on 2024-03-31 (Sunday) and falls back on 2024-10-27
+        let friday_before_spring = at(&Berlin, (2024, 3, 29), (12, 0));
+        assert_eq!(
+            hours.next_day_start_in(&Berlin, friday_before_spring),
+            Some(at(&Berlin, (2024, 4, 1), (9, 0)))
+        );
+        assert!(hours.is_working_time_in(&Berlin, at(&Berlin, (2024, 10, 28), (9, 0))));
+        assert!(!hours.is_working_time_in(&Berlin, at(&Berlin, (2024, 10, 28), (8, 59))));
+    }
+
+    #[test]
+    fn times_skipped_by_dst_start_after_the_gap() {
+        let night_shift = WorkingHours {
+            enabled: true,
+            days: vec![vec![]; 6]
+                .into_iter()
+                .chain([vec![TimeRange {
+                    start: "02:30".to_string(),
+                    end: "04:00".to_string(),
+                }]])
+                .collect(),
+        };
+        // 02:30 doesn't exist on 2024-03-31 in Berlin; 03:30 CEST does
+        let sunday = at(&Berlin, (2024, 3, 30), (12, 0));
+        assert_eq!(
+            night_shift.next_day_start_in(&Berlin, sunday),
+            Some(at(&Berlin, (2024, 3, 31), (3, 30)))
+        );
+    }
+
+    #[test]
+    fn disabled_schedule_is_always_working_time() {
+        let hours = WorkingHours::default();
+        assert!(hours.is_working_time_in(&Utc, at(&Utc, (2024, 6, 8), (23, 0))));
+        assert_eq!(hours.window_end_in(&Utc, at(&Utc, (2024, 6, 3), (12, 0))), None);
+    }
+
+    #[test]
+    fn validation_rejects_bad_ranges() {
+        let with_monday = |start: &str, end: &str| {
+            let mut hours = office_hours();
+            hours.days[0] = vec![TimeRange {
+                start: start.to_string(),
+                end: end.to_string(),
+            }];
+            hours.validate()
+        };
+        assert_eq!(with_monday("13:00", "24:00"), Ok(()));
+        assert!(with_monday("17:00", "09:00").is_err());
+        assert!(with_monday("9:00", "17:00").is_err());
+        assert!(with_monday("09:00", "24:30").is_err());
+    }
+}
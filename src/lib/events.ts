@@ -0,0 +1,137 @@
+// Mirrors src-tauri/src/events.rs: event names and payloads pushed by the backend.
+import { listen, type UnlistenFn } from '@tauri-apps/api/event'
+import type { BreakSettings } from '$lib/schemas/settings'
+
+/** Fixed breaks (lunch, ...) and break kinds (eye rest, ...) carry the name they have in the settings. */
+export type BreakType =
+  | 'Microbreak'
+  | 'Longbreak'
+  | 'PomodoroBreak'
+  | 'PomodoroLongBreak'
+  | { Fixed: string }
+  | { Custom: string }
+
+/** Title for a break: microbreak or long break, or the fixed break's or break kind's own name. */
+export const breakTypeName = (breakType: BreakType): string => {
+  if (breakType === 'Microbreak') return 'Microbreak'
+  if (breakType === 'Longbreak') return 'Long Break'
+  if (breakType === 'PomodoroBreak') return 'Pomodoro Break'
+  if (breakType === 'PomodoroLongBreak') return 'Long Pomodoro Break'
+  return 'Fixed' in breakType ? breakType.Fixed : breakType.Custom
+}
+
+/** Break types as plain strings, since named ones are objects and don't compare with `===`. */
+export const breakTypeKey = (breakType: BreakType | null): string | null => {
+  if (breakType === null || typeof breakType === 'string') return breakType
+  return 'Fixed' in breakType ? `Fixed:${breakType.Fixed}` : `Custom:${breakType.Custom}`
+}
+
+export type EscalationStep = 'Fullscreen' | 'LongerMicrobreaks' | 'Strict'
+
+/** A running pomodoro work session. Times are Unix ms. */
+export interface Pomodoro {
+  started_at: number
+  ends_at: number
+  label: string | null
+}
+
+export type BreakPhase = 'Scheduled' | 'Warning' | 'Active' | 'Paused' | 'Disabled'
+
+export interface BreakState {
+  break_type: BreakType | null
+  break_number: number
+  microbreaks_since_longbreak: number
+  phase: BreakPhase
+  postpone_count: number
+  postpones_this_break: number
+  last_postponed_at: number | null
+  skip_count: number
+  natural_break_count: number
+  longbreak_cycle_started_at: number | null
+  break_started_at: number | null
+  break_ends_at: number | null
+  strict: boolean
+  paused_until: number | null
+  consecutive_dodges: number
+  escalation_level: number
+  escalation: EscalationStep[]
+  break_due_at: number | null
+  warning_ends_at: number | null
+  last_fixed_break_at: number | null
+  kind_due_at: Record<string, number>
+  pomodoro: Pomodoro | null
+  pomodoros_since_long_break: number
+  reported_until: number | null
+}
+
+export interface ProfileList {
+  active: string | null
+  names: string[]
+}
+
+export interface ImportReport {
+  source: 'Stretchly' | 'Workrave'
+  settings: BreakSettings
+  mapped: string[]
+  unmapped: string[]
+  profile: string | null
+}
+
+export type SummaryPeriod = 'Day' | 'Week'
+
+export type ExportFormat = 'Csv' | 'JsonLines'
+
+export interface HistorySummary {
+  from: number
+  to: number
+  breaks_taken: number
+  breaks_skipped: number
+  postpones: number
+  compliance_rate: number | null
+  breaks_started: number
+  average_delay_ms: number | null
+  longest_stretch_ms: number
+  pomodoros_completed: number
+}
+
+export interface SchedulerState {
+  scheduled_break_time: number | null
+  current_break_type: BreakType | null
+  time_left_ms: number | null
+  active_time_only: boolean
+  postpones_left: number
+  postpone_available_at: number | null
+}
+
+/** `break-state-changed`: any lifecycle transition or change to the schedule. */
+export interface SchedulerEvent {
+  state: BreakState
+  schedule: SchedulerState
+}
+
+/** `break-tick`: every second while a break is on screen. Times are Unix ms. */
+export interface BreakTick {
+  break_type: BreakType | null
+  started_at: number
+  ends_at: number
+  time_left_ms: number
+}
+
+export const BREAK_STATE_CHANGED = 'break-state-changed'
+export const BREAK_TICK = 'break-tick'
+export const SETTINGS_CHANGED = 'settings-changed'
+export const PROFILES_CHANGED = 'profiles-changed'
+
+export const onBreakStateChanged = (handler: (event: SchedulerEvent) => void): Promise<UnlistenFn> =>
+  listen<SchedulerEvent>(BREAK_STATE_CHANGED, (event) => handler(event.payload))
+
+export const onBreakTick = (handler: (tick: BreakTick) => void): Promise<UnlistenFn> =>
+  listen<BreakTick>(BREAK_TICK, (event) => handler(event.payload))
+
+/** `settings-changed`: settings were saved. */
+export const onSettingsChanged = (handler: (settings: BreakSettings) => void): Promise<UnlistenFn> =>
+  listen<BreakSettings>(SETTINGS_CHANGED, (event) => handler(event.payload))
+
+/** `profiles-changed`: a profile was created, renamed, deleted or activated. */
+export const onProfilesChanged = (handler: (profiles: ProfileList) => void): Promise<UnlistenFn> =>
+  listen<ProfileList>(PROFILES_CHANGED, (event) => handler(event.payload))
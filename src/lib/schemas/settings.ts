@@ -1,5 +1,87 @@
 import { z } from 'zod';
 
+const TIME = /^(([01]\d|2[0-3]):[0-5]\d|24:00)$/;
+
+const toMinutes = (time: string) => {
+  const [hours, minutes] = time.split(':').map(Number);
+  return hours * 60 + minutes;
+};
+
+const timeRangeSchema = z.object({
+  start: z.string().regex(TIME, "Use HH:MM for working hours"),
+  end: z.string().regex(TIME, "Use HH:MM for working hours"),
+})
+  .refine((range) => toMinutes(range.start) < toMinutes(range.end), "Working hours must end after they start");
+
+export const workingHoursSchema = z.object({
+  enabled: z.boolean(),
+  // Monday first
+  days: z.array(
+    z.array(timeRangeSchema)
+      .refine((ranges) => ranges.every((r, i) => i === 0 || toMinutes(ranges[i - 1].end) <= toMinutes(r.start)), "Working hours within a day must be in order and not overlap"),
+  ).length(7),
+});
+
+// "[days] HH:MM", days being daily, weekdays, weekends or e.g. Mon,Wed or Mon-Fri
+const DAY = '(mon|tue|wed|thu|fri|sat|sun)'
+const FIXED_AT = new RegExp(
+  `^((daily|weekdays|weekends|${DAY}(-${DAY})?(,${DAY}(-${DAY})?)*)\\s+)?([01]\\d|2[0-3]):[0-5]\\d$`,
+  'i',
+)
+
+export const fixedBreakSchema = z.object({
+  name: z.string().trim().min(1, "Fixed breaks need a name"),
+  at: z.string().regex(FIXED_AT, "Use e.g. \"12:30\", \"weekdays 12:30\" or \"Mon-Thu 15:00\""),
+  duration_minutes: z.number()
+    .int()
+    .min(1, "Fixed breaks must be at least 1 minute")
+    .max(120, "Fixed breaks cannot exceed 120 minutes"),
+  strict: z.boolean(),
+});
+
+export const breakKindSchema = z.object({
+  name: z.string().trim().min(1, "Break kinds need a name"),
+  interval_minutes: z.number()
+    .int()
+    .min(1, "Break kind interval must be at least 1 minute")
+    .max(480, "Break kind interval cannot exceed 8 hours"),
+  duration_seconds: z.number()
+    .int()
+    .min(5, "Break kind duration must be at least 5 seconds")
+    .max(3600, "Break kind duration cannot exceed an hour"),
+  strict: z.boolean(),
+  presentation: z.enum(['Window', 'Fullscreen']),
+  message: z.string().max(200, "Message cannot exceed 200 characters"),
+});
+
+export const pomodoroSchema = z.object({
+  mode: z.enum(['Off', 'Alongside', 'Instead']),
+  work_minutes: z.number()
+    .int()
+    .min(5, "Pomodoros must be at least 5 minutes")
+    .max(120, "Pomodoros cannot exceed 120 minutes"),
+  short_break_minutes: z.number()
+    .int()
+    .min(1, "Pomodoro breaks must be at least 1 minute")
+    .max(30, "Pomodoro breaks cannot exceed 30 minutes"),
+  long_break_minutes: z.number()
+    .int()
+    .min(1, "Long pomodoro breaks must be at least 1 minute")
+    .max(60, "Long pomodoro breaks cannot exceed 60 minutes"),
+  long_break_every: z.number()
+    .int()
+    .min(1, "Long pomodoro breaks come after at least 1 pomodoro")
+    .max(10, "Long pomodoro breaks come after at most 10 pomodoros"),
+});
+
+export const reportSchema = z.object({
+  enabled: z.boolean(),
+  format: z.enum(['Markdown', 'Html']),
+  // Empty for the default folder in the user's documents
+  folder: z.string().refine((folder) => folder === '' || /^([a-zA-Z]:[\\/]|[\\/])/.test(folder), "Report folder must be an absolute path"),
+  notify: z.boolean(),
+});
+
 export const breakSettingsSchema = z.object({
   microbreak_interval_minutes: z.number()
     .int()
@@ -11,17 +93,95 @@ export const breakSettingsSchema = z.object({
     .min(5, "Microbreak duration must be at least 5 seconds")
     .max(300, "Microbreak duration cannot exceed 5 minutes"),
 
+  longbreak_schedule: z.enum(['Microbreaks', 'Minutes']),
+
   longbreak_interval_microbreaks: z.number()
     .int()
     .min(1, "Long break interval must be at least 1 microbreak")
     .max(10, "Long break interval cannot exceed 10 microbreaks"),
 
+  longbreak_interval_minutes: z.number()
+    .int()
+    .min(10, "Long break interval must be at least 10 minutes")
+    .max(240, "Long break interval cannot exceed 240 minutes"),
+
   longbreak_duration_minutes: z.number()
     .int()
     .min(1, "Long break duration must be at least 1 minute")
     .max(60, "Long break duration cannot exceed 60 minutes"),
 
   fullscreen_breaks: z.boolean(),
-});
+
+  warning_seconds: z.number()
+    .int()
+    .min(0, "Break warning cannot be negative")
+    .max(300, "Break warning cannot exceed 5 minutes"),
+
+  postpone_durations_minutes: z.array(
+    z.number()
+      .int()
+      .min(1, "Postpone durations must be at least 1 minute")
+      .max(60, "Postpone durations cannot exceed 60 minutes"),
+  )
+    .min(1, "Offer at least one postpone duration")
+    .max(5, "Offer at most 5 postpone durations")
+    .refine((durations) => durations.every((d, i) => i === 0 || durations[i - 1] < d), "List postpone durations shortest first, without repeats"),
+
+  max_postpones_per_break: z.number()
+    .int()
+    .min(0, "Postpone limit cannot be negative")
+    .max(10, "Postpone limit cannot exceed 10 per break"),
+
+  postpone_cooldown_seconds: z.number()
+    .int()
+    .min(0, "Postpone cooldown cannot be negative")
+    .max(3600, "Postpone cooldown cannot exceed an hour"),
+
+  strict_microbreaks: z.boolean(),
+
+  strict_longbreaks: z.boolean(),
+
+  escalation_after_dodges: z.number()
+    .int()
+    .min(0, "Escalation threshold cannot be negative")
+    .max(10, "Escalation threshold cannot exceed 10 skips or postpones"),
+
+  escalation_steps: z.array(z.enum(['Fullscreen', 'LongerMicrobreaks', 'Strict']))
+    .refine((steps) => new Set(steps).size === steps.length, "Escalation steps must not repeat"),
+
+  escalation_extra_seconds: z.number()
+    .int()
+    .min(0, "Extra time cannot be negative")
+    .max(300, "Extra time cannot exceed 5 minutes"),
+
+  count_active_time_only: z.boolean(),
+
+  working_hours: workingHoursSchema,
+
+  fixed_breaks: z.array(fixedBreakSchema)
+    .max(10, "At most 10 fixed breaks")
+    .refine((breaks) => new Set(breaks.map((b) => b.name)).size === breaks.length, "Fixed breaks need different names"),
+
+  break_kinds: z.array(breakKindSchema)
+    .max(10, "At most 10 break kinds")
+    .refine((kinds) => new Set(kinds.map((k) => k.name)).size === kinds.length, "Break kinds need different names"),
+
+  pomodoro: pomodoroSchema,
+
+  reports: reportSchema,
+})
+  .refine(
+    (s) => s.longbreak_schedule !== 'Minutes' || s.longbreak_interval_minutes > s.microbreak_interval_minutes,
+    { message: "Long break interval must be longer than the microbreak interval", path: ['longbreak_interval_minutes'] },
+  )
+  .refine(
+    (s) => s.longbreak_duration_minutes * 60 > s.microbreak_duration_seconds,
+    { message: "Long breaks must be longer than microbreaks", path: ['longbreak_duration_minutes'] },
+  );
 
 export type BreakSettings = z.infer<typeof breakSettingsSchema>;
+export type TimeRange = z.infer<typeof timeRangeSchema>;
+export type FixedBreak = z.infer<typeof fixedBreakSchema>;
+export type BreakKind = z.infer<typeof breakKindSchema>;
+export type PomodoroSettings = z.infer<typeof pomodoroSchema>;
+export type ReportSettings = z.infer<typeof reportSchema>;
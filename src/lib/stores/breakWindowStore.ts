@@ -1,8 +1,9 @@
 import { writable } from 'svelte/store'
+import type { BreakType } from '$lib/events'
 
 export interface BreakWindow {
   isVisible: boolean
-  breakType: 'Microbreak' | 'Longbreak' | null
+  breakType: BreakType | null
   timeLeft: number
   isFullscreen: boolean
 }
@@ -19,7 +20,7 @@ function createBreakWindowStore() {
 
   return {
     subscribe,
-    showWindow: (breakType: 'Microbreak' | 'Longbreak') =>
+    showWindow: (breakType: BreakType) =>
       update((window) => ({
         ...window,
         isVisible: true,